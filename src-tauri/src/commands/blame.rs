@@ -0,0 +1,47 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::git::{blame, blame::BlameOptions, blame::BlameRange, executor::GitExecutor};
+
+/// Event emitted with each batch of ranges while a file is blamed
+const BLAME_PROGRESS_EVENT: &str = "blame-progress";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlameProgress {
+    /// Identifier chosen by the caller to match events to its request
+    pub request_id: String,
+    pub ranges: Vec<BlameRange>,
+}
+
+#[tauri::command]
+pub async fn blame_file(
+    repo_path: String,
+    file_path: String,
+    options: Option<BlameOptions>,
+) -> Result<Vec<BlameRange>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    blame::blame(&executor, &file_path, &options.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+/// Blame a file, emitting `blame-progress` events as ranges are found.
+/// Resolves once the whole file has been blamed.
+#[tauri::command]
+pub async fn blame_file_incremental(
+    app: AppHandle,
+    repo_path: String,
+    file_path: String,
+    options: Option<BlameOptions>,
+    request_id: String,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    blame::blame_incremental(&executor, &file_path, &options.unwrap_or_default(), |ranges| {
+        let _ = app.emit(
+            BLAME_PROGRESS_EVENT,
+            BlameProgress {
+                request_id: request_id.clone(),
+                ranges,
+            },
+        );
+    })
+    .map_err(|e| e.to_string())
+}
@@ -1,4 +1,9 @@
-use crate::git::{branch, executor::GitExecutor, types::Branch};
+use tauri::AppHandle;
+
+use crate::git::{branch, executor::GitExecutor, signing::SignOptions, types::Branch};
+
+use super::hooks::emit_hook_output;
+use super::signing::resolve_signing;
 
 #[tauri::command]
 pub async fn get_branches(repo_path: String) -> Result<Vec<Branch>, String> {
@@ -54,12 +59,17 @@ pub async fn delete_remote_branch(
 
 #[tauri::command]
 pub async fn merge_branch(
+    app: AppHandle,
     repo_path: String,
     name: String,
     no_ff: bool,
+    signing: Option<SignOptions>,
+    request_id: Option<String>,
 ) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    branch::merge_branch(&executor, &name, no_ff).map_err(|e| e.to_string())
+    let signing = resolve_signing(&executor, signing, false)?;
+    branch::merge_branch(&executor, &name, no_ff, &signing, emit_hook_output(&app, request_id))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -68,6 +78,14 @@ pub async fn rebase_branch(repo_path: String, onto: String) -> Result<(), String
     branch::rebase_branch(&executor, &onto).map_err(|e| e.to_string())
 }
 
+/// Fold fixup!, squash! and amend! commits made after `base` (everything
+/// when not set) into their targets
+#[tauri::command]
+pub async fn autosquash_rebase(repo_path: String, base: Option<String>) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    branch::autosquash(&executor, base.as_deref()).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn abort_merge(repo_path: String) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
@@ -1,13 +1,57 @@
-use crate::git::{commit, executor::GitExecutor};
+use tauri::AppHandle;
 
+use crate::git::{
+    commit::{self, CherryPickOptions, CherryPickProgress, CommitOptions, FixupKind},
+    executor::GitExecutor,
+    signing::SignOptions,
+};
+
+use super::hooks::emit_hook_output;
+use super::signing::resolve_signing;
+
+/// Create a commit. `amend` is kept for callers that do not pass `options`.
+/// Output of git and its hooks is emitted as `hook-output` events.
 #[tauri::command]
 pub async fn create_commit(
+    app: AppHandle,
     repo_path: String,
     message: String,
-    amend: bool,
+    amend: Option<bool>,
+    options: Option<CommitOptions>,
+    request_id: Option<String>,
 ) -> Result<String, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    commit::create_commit(&executor, &message, amend).map_err(|e| e.to_string())
+    let mut options = options.unwrap_or_default();
+    options.amend |= amend.unwrap_or(false);
+    options.signing = resolve_signing(&executor, Some(options.signing), false)?;
+    commit::create_commit(&executor, &message, &options, emit_hook_output(&app, request_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Create a fixup!, squash! or amend! commit of the staged changes for
+/// `target`, to be folded in with `autosquash_rebase`
+#[tauri::command]
+pub async fn create_fixup_commit(
+    app: AppHandle,
+    repo_path: String,
+    target: String,
+    kind: FixupKind,
+    message: Option<String>,
+    options: Option<CommitOptions>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let mut options = options.unwrap_or_default();
+    options.signing = resolve_signing(&executor, Some(options.signing), false)?;
+    commit::create_fixup_commit(
+        &executor,
+        &target,
+        kind,
+        message.as_deref(),
+        &options,
+        emit_hook_output(&app, request_id),
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -17,9 +61,24 @@ pub async fn get_last_commit_message(repo_path: String) -> Result<String, String
 }
 
 #[tauri::command]
-pub async fn cherry_pick(repo_path: String, hash: String) -> Result<(), String> {
+pub async fn cherry_pick(repo_path: String, hash: String, signing: Option<SignOptions>) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    commit::cherry_pick(&executor, &hash).map_err(|e| e.to_string())
+    let signing = resolve_signing(&executor, signing, false)?;
+    commit::cherry_pick(&executor, &hash, &signing).map_err(|e| e.to_string())
+}
+
+/// Pick commits and "A..B" ranges in order. A conflict stops the sequence
+/// and is reported in the returned progress rather than as an error.
+#[tauri::command]
+pub async fn cherry_pick_commits(
+    repo_path: String,
+    revisions: Vec<String>,
+    options: Option<CherryPickOptions>,
+) -> Result<CherryPickProgress, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let mut options = options.unwrap_or_default();
+    options.signing = resolve_signing(&executor, Some(options.signing), false)?;
+    commit::cherry_pick_commits(&executor, &revisions, &options).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -41,11 +100,17 @@ pub async fn abort_revert(repo_path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn continue_cherry_pick(repo_path: String) -> Result<(), String> {
+pub async fn continue_cherry_pick(repo_path: String) -> Result<CherryPickProgress, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
     commit::continue_cherry_pick(&executor).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn skip_cherry_pick(repo_path: String) -> Result<CherryPickProgress, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    commit::skip_cherry_pick(&executor).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn continue_revert(repo_path: String) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
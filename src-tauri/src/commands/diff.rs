@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use crate::git::{diff, executor::GitExecutor, types::FileDiff};
+use crate::git::{
+    diff,
+    executor::GitExecutor,
+    types::{DiffCursor, DiffHunkPage, DiffLimits, DiffTarget, FileDiff},
+};
 
 #[tauri::command]
 pub async fn get_file_diff(
@@ -8,27 +12,95 @@ pub async fn get_file_diff(
     file_path: String,
     staged: bool,
     ignore_cr: Option<bool>,
+    limits: Option<DiffLimits>,
 ) -> Result<FileDiff, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::get_file_diff(&executor, &file_path, staged, ignore_cr.unwrap_or(true)).map_err(|e| e.to_string())
+    let mut file_diff = diff::get_file_diff(&executor, &file_path, staged, ignore_cr.unwrap_or(true))
+        .map_err(|e| e.to_string())?;
+    diff::apply_file_limits(&mut file_diff, &limits.unwrap_or_default());
+    Ok(file_diff)
 }
 
 #[tauri::command]
-pub async fn get_commit_diff(repo_path: String, hash: String) -> Result<Vec<FileDiff>, String> {
+pub async fn get_commit_diff(
+    repo_path: String,
+    hash: String,
+    limits: Option<DiffLimits>,
+) -> Result<Vec<FileDiff>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let mut diffs = diff::get_commit_diff(&executor, &hash).map_err(|e| e.to_string())?;
+    diff::apply_limits(&mut diffs, &limits.unwrap_or_default());
+    Ok(diffs)
+}
+
+#[tauri::command]
+pub async fn get_file_diff_at_commit(
+    repo_path: String,
+    hash: String,
+    file_path: String,
+    old_path: Option<String>,
+    limits: Option<DiffLimits>,
+) -> Result<FileDiff, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let mut file_diff = diff::get_file_diff_at_commit(&executor, &hash, &file_path, old_path.as_deref())
+        .map_err(|e| e.to_string())?;
+    diff::apply_file_limits(&mut file_diff, &limits.unwrap_or_default());
+    Ok(file_diff)
+}
+
+#[tauri::command]
+pub async fn get_stash_diff(
+    repo_path: String,
+    index: u32,
+    limits: Option<DiffLimits>,
+) -> Result<Vec<FileDiff>, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::get_commit_diff(&executor, &hash).map_err(|e| e.to_string())
+    let mut diffs = diff::get_stash_diff(&executor, index).map_err(|e| e.to_string())?;
+    diff::apply_limits(&mut diffs, &limits.unwrap_or_default());
+    Ok(diffs)
 }
 
 #[tauri::command]
-pub async fn get_stash_diff(repo_path: String, index: u32) -> Result<Vec<FileDiff>, String> {
+pub async fn get_untracked_file_diff(
+    repo_path: String,
+    file_path: String,
+    limits: Option<DiffLimits>,
+) -> Result<FileDiff, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::get_stash_diff(&executor, index).map_err(|e| e.to_string())
+    let mut file_diff = diff::get_untracked_file_diff(&executor, &file_path).map_err(|e| e.to_string())?;
+    diff::apply_file_limits(&mut file_diff, &limits.unwrap_or_default());
+    Ok(file_diff)
 }
 
+/// Fetch more hunks of a file whose diff was truncated by `DiffLimits`,
+/// from line `start_line` (0 when not set) of hunk `start_hunk`
 #[tauri::command]
-pub async fn get_untracked_file_diff(repo_path: String, file_path: String) -> Result<FileDiff, String> {
+#[allow(clippy::too_many_arguments)]
+pub async fn get_diff_hunks(
+    repo_path: String,
+    target: DiffTarget,
+    file_path: String,
+    old_path: Option<String>,
+    start_hunk: usize,
+    start_line: Option<usize>,
+    ignore_cr: Option<bool>,
+    limits: Option<DiffLimits>,
+) -> Result<DiffHunkPage, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::get_untracked_file_diff(&executor, &file_path).map_err(|e| e.to_string())
+    let start = DiffCursor {
+        hunk: start_hunk,
+        line: start_line.unwrap_or(0),
+    };
+    diff::get_diff_hunks(
+        &executor,
+        &target,
+        &file_path,
+        old_path.as_deref(),
+        start,
+        ignore_cr.unwrap_or(true),
+        &limits.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -36,9 +108,11 @@ pub async fn stage_lines(
     repo_path: String,
     file_path: String,
     line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<String>,
 ) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::stage_lines(&executor, &file_path, line_indices_by_hunk).map_err(|e| e.to_string())
+    diff::stage_lines(&executor, &file_path, line_indices_by_hunk, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -46,9 +120,59 @@ pub async fn unstage_lines(
     repo_path: String,
     file_path: String,
     line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    diff::unstage_lines(&executor, &file_path, line_indices_by_hunk, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn discard_lines(
+    repo_path: String,
+    file_path: String,
+    line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    diff::discard_lines(&executor, &file_path, line_indices_by_hunk, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stage_hunks(
+    repo_path: String,
+    file_path: String,
+    hunk_indices: Vec<usize>,
+    expected_fingerprint: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    diff::stage_hunks(&executor, &file_path, &hunk_indices, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unstage_hunks(
+    repo_path: String,
+    file_path: String,
+    hunk_indices: Vec<usize>,
+    expected_fingerprint: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    diff::unstage_hunks(&executor, &file_path, &hunk_indices, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn discard_hunks(
+    repo_path: String,
+    file_path: String,
+    hunk_indices: Vec<usize>,
+    expected_fingerprint: Option<String>,
 ) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    diff::unstage_lines(&executor, &file_path, line_indices_by_hunk).map_err(|e| e.to_string())
+    diff::discard_hunks(&executor, &file_path, &hunk_indices, expected_fingerprint.as_deref())
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
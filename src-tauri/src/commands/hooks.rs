@@ -0,0 +1,44 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::git::{
+    executor::GitExecutor,
+    hooks::{self, HookInfo, HookOutput},
+};
+
+/// Event emitted with each line printed by a commit, merge or push and the
+/// hooks it runs
+const HOOK_OUTPUT_EVENT: &str = "hook-output";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookOutputEvent {
+    /// Identifier chosen by the caller to match events to its request
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub output: HookOutput,
+}
+
+/// Callback emitting `hook-output` events for a request
+pub(crate) fn emit_hook_output(app: &AppHandle, request_id: Option<String>) -> impl FnMut(HookOutput) + '_ {
+    move |output| {
+        let _ = app.emit(
+            HOOK_OUTPUT_EVENT,
+            HookOutputEvent {
+                request_id: request_id.clone(),
+                output,
+            },
+        );
+    }
+}
+
+#[tauri::command]
+pub async fn list_hooks(repo_path: String) -> Result<Vec<HookInfo>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    hooks::list_hooks(&executor).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_hook_enabled(repo_path: String, name: String, enabled: bool) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    hooks::set_hook_enabled(&executor, &name, enabled).map_err(|e| e.to_string())
+}
@@ -1,11 +1,133 @@
-use crate::git::{executor::GitExecutor, log, types::Commit};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+
+use crate::git::{
+    commit_cache::{self, commit_cache_dir},
+    executor::GitExecutor,
+    graph::{self, GraphPage, GraphState},
+    log::{self, LogQuery},
+    search::{self, CommitLocation},
+    signature,
+    types::{Commit, CommitDetails, CommitSignature, FileLogEntry},
+};
+
+/// Commit cache of a repository, used when the query asks for the whole history
+fn cache_dir_for(app: &AppHandle, repo_path: &str, query: &LogQuery) -> Result<Option<PathBuf>, String> {
+    if !query.is_unfiltered() {
+        return Ok(None);
+    }
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(Some(commit_cache_dir(&app_data_dir, repo_path)))
+}
 
 #[tauri::command]
 pub async fn get_commit_log(
+    app: AppHandle,
+    repo_path: String,
+    count: u32,
+    skip: u32,
+    query: Option<LogQuery>,
+    with_stats: Option<bool>,
+) -> Result<Vec<Commit>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let query = query.unwrap_or_default();
+    let with_stats = with_stats.unwrap_or(false);
+
+    // Stats are not cached, they need a diff of each commit
+    if !with_stats {
+        if let Some(cache_dir) = cache_dir_for(&app, &repo_path, &query)? {
+            return commit_cache::get_cached_commit_log(&executor, &cache_dir, count, skip)
+                .map_err(|e| e.to_string());
+        }
+    }
+    log::get_commit_log(&executor, &query, count, skip, with_stats).map_err(|e| e.to_string())
+}
+
+/// Get a commit with its changed files and line counts, without hunks
+#[tauri::command]
+pub async fn get_commit_details(repo_path: String, hash: String) -> Result<CommitDetails, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    log::get_commit_details(&executor, &hash).map_err(|e| e.to_string())
+}
+
+/// Load a page of the log together with its graph layout. Pass the `state`
+/// returned with the previous page to continue the lanes from it.
+#[tauri::command]
+pub async fn get_commit_graph(
+    app: AppHandle,
     repo_path: String,
     count: u32,
     skip: u32,
+    query: Option<LogQuery>,
+    state: Option<GraphState>,
+) -> Result<GraphPage, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let query = query.unwrap_or_default();
+    match cache_dir_for(&app, &repo_path, &query)? {
+        Some(cache_dir) => graph::get_cached_commit_graph(&executor, &cache_dir, count, skip, state),
+        None => graph::get_commit_graph(&executor, &query, count, skip, state),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_file_log(
+    repo_path: String,
+    file_path: String,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<FileLogEntry>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    log::get_file_log(&executor, &file_path, count, skip).map_err(|e| e.to_string())
+}
+
+/// Verify the signatures of the given commits, keyed by hash
+#[tauri::command]
+pub async fn get_commit_signatures(
+    repo_path: String,
+    hashes: Vec<String>,
+) -> Result<HashMap<String, CommitSignature>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    signature::get_commit_signatures(&executor, &hashes).map_err(|e| e.to_string())
+}
+
+/// Resolve a revision expression and find the page of the log holding it
+#[tauri::command]
+pub async fn locate_commit(
+    app: AppHandle,
+    repo_path: String,
+    revision: String,
+    page_size: u32,
+    query: Option<LogQuery>,
+) -> Result<CommitLocation, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    let query = query.unwrap_or_default();
+    match cache_dir_for(&app, &repo_path, &query)? {
+        Some(cache_dir) => search::locate_cached_commit(&executor, &cache_dir, &revision, page_size),
+        None => search::locate_commit(&executor, &revision, &query, page_size),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Search commits by hash prefix or fuzzy subject match
+#[tauri::command]
+pub async fn search_commits(
+    app: AppHandle,
+    repo_path: String,
+    text: String,
+    limit: usize,
+    query: Option<LogQuery>,
 ) -> Result<Vec<Commit>, String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    log::get_commit_log(&executor, count, skip).map_err(|e| e.to_string())
+    let query = query.unwrap_or_default();
+    match cache_dir_for(&app, &repo_path, &query)? {
+        Some(cache_dir) => search::search_cached_commits(&executor, &cache_dir, &text, limit),
+        None => search::search_commits(&executor, &text, &query, limit),
+    }
+    .map_err(|e| e.to_string())
 }
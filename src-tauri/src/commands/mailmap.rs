@@ -0,0 +1,24 @@
+use crate::git::{
+    executor::GitExecutor,
+    mailmap::{self, MailmapEntry},
+};
+
+#[tauri::command]
+pub async fn get_mailmap(repo_path: String) -> Result<Vec<MailmapEntry>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    mailmap::get_mailmap(&executor).map_err(|e| e.to_string())
+}
+
+/// Replace the entries of the repository's .mailmap
+#[tauri::command]
+pub async fn set_mailmap(repo_path: String, entries: Vec<MailmapEntry>) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    mailmap::set_mailmap(&executor, &entries).map_err(|e| e.to_string())
+}
+
+/// Resolve "Name <email>" identities through the mailmap, e.g. to preview an entry
+#[tauri::command]
+pub async fn check_mailmap(repo_path: String, identities: Vec<String>) -> Result<Vec<String>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    mailmap::check_mailmap(&executor, &identities).map_err(|e| e.to_string())
+}
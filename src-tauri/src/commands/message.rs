@@ -0,0 +1,18 @@
+use crate::git::{
+    executor::GitExecutor,
+    message::{self, CommitTemplate, LintConfig, LintDiagnostic},
+};
+
+/// Templates from commit.template and the repository's .gitmessage files
+#[tauri::command]
+pub async fn get_commit_templates(repo_path: String) -> Result<Vec<CommitTemplate>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    message::get_commit_templates(&executor).map_err(|e| e.to_string())
+}
+
+/// Check a commit message before it is committed, with the default rules
+/// when no config is given
+#[tauri::command]
+pub async fn lint_commit_message(message: String, config: Option<LintConfig>) -> Result<Vec<LintDiagnostic>, String> {
+    Ok(message::lint_commit_message(&message, &config.unwrap_or_default()))
+}
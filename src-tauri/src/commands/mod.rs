@@ -2,22 +2,40 @@ pub mod repository;
 pub mod status;
 pub mod diff;
 pub mod commit;
+pub mod hooks;
+pub mod message;
 pub mod log;
+pub mod blame;
+pub mod stats;
+pub mod mailmap;
+pub mod notes;
 pub mod branch;
+pub mod reflog;
 pub mod tag;
 pub mod remote;
 pub mod config;
 pub mod keys;
+pub mod signing;
 pub mod stash;
+pub mod patch;
 
 pub use repository::*;
 pub use status::*;
 pub use diff::*;
 pub use commit::*;
+pub use hooks::*;
+pub use message::*;
 pub use log::*;
+pub use blame::*;
+pub use stats::*;
+pub use mailmap::*;
+pub use notes::*;
 pub use branch::*;
+pub use reflog::*;
 pub use tag::*;
 pub use remote::*;
 pub use config::*;
 pub use keys::*;
+pub use signing::*;
 pub use stash::*;
+pub use patch::*;
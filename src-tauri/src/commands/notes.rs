@@ -0,0 +1,45 @@
+use crate::git::{executor::GitExecutor, notes, types::CommitNote};
+
+#[tauri::command]
+pub async fn get_commit_notes(
+    repo_path: String,
+    hash: String,
+    notes_refs: Option<Vec<String>>,
+) -> Result<Vec<CommitNote>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    notes::get_commit_notes(&executor, &hash, &notes_refs.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_display_notes_refs(repo_path: String) -> Result<Vec<String>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    notes::get_display_notes_refs(&executor).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_note(
+    repo_path: String,
+    hash: String,
+    message: String,
+    notes_ref: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    notes::add_note(&executor, &hash, &message, notes_ref.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn edit_note(
+    repo_path: String,
+    hash: String,
+    message: String,
+    notes_ref: Option<String>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    notes::edit_note(&executor, &hash, &message, notes_ref.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_note(repo_path: String, hash: String, notes_ref: Option<String>) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    notes::remove_note(&executor, &hash, notes_ref.as_deref()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,64 @@
+use crate::git::{executor::GitExecutor, patch, patch::PatchCheck, patch::PatchFormat};
+
+#[tauri::command]
+pub async fn export_working_diff(
+    repo_path: String,
+    output_path: String,
+    staged: bool,
+    paths: Option<Vec<String>>,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::export_working_diff(&executor, &output_path, staged, &paths.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_commits(
+    repo_path: String,
+    from: Option<String>,
+    to: String,
+    format: PatchFormat,
+    output_path: String,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::export_commits(&executor, from.as_deref(), &to, format, &output_path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn apply_patch(
+    repo_path: String,
+    patch_path: String,
+    three_way: bool,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::apply_patch(&executor, &patch_path, three_way).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn check_patch(
+    repo_path: String,
+    patch_path: String,
+    three_way: bool,
+) -> Result<PatchCheck, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::check_patch(&executor, &patch_path, three_way).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn abort_am(repo_path: String) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::abort_am(&executor).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn continue_am(repo_path: String) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::continue_am(&executor).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn skip_am(repo_path: String) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    patch::skip_am(&executor).map_err(|e| e.to_string())
+}
@@ -0,0 +1,32 @@
+use crate::git::{
+    executor::GitExecutor,
+    reflog::{self, ReflogEntry},
+};
+
+#[tauri::command]
+pub async fn get_reflog(
+    repo_path: String,
+    reference: Option<String>,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<ReflogEntry>, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    reflog::get_reflog(&executor, reference.as_deref(), count, skip).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_branch_from_reflog(
+    repo_path: String,
+    name: String,
+    oid: String,
+    checkout: bool,
+) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    reflog::create_branch_from_reflog(&executor, &name, &oid, checkout).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn checkout_reflog_entry(repo_path: String, oid: String) -> Result<(), String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    reflog::checkout_reflog_entry(&executor, &oid).map_err(|e| e.to_string())
+}
@@ -1,14 +1,17 @@
 use std::path::PathBuf;
+use tauri::AppHandle;
 use crate::git::{executor::GitExecutor, remote, types::Remote};
 use crate::keys::{needs_unlock, get_decrypted_key_path};
 
+use super::hooks::emit_hook_output;
+
 /// Error type that the frontend can distinguish for key unlock prompts
 const KEY_LOCKED_ERROR: &str = "SSH_KEY_LOCKED:";
 
 /// Resolve the SSH key path, decrypting if needed
 /// Returns None if no key specified, Some(path) otherwise
 /// Returns an error starting with KEY_LOCKED_ERROR if the key needs unlocking
-fn resolve_ssh_key(ssh_key_path: Option<&str>) -> Result<Option<String>, String> {
+pub(crate) fn resolve_ssh_key(ssh_key_path: Option<&str>) -> Result<Option<String>, String> {
     let Some(key_path) = ssh_key_path else {
         return Ok(None);
     };
@@ -80,7 +83,9 @@ pub async fn pull_remote(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn push_remote(
+    app: AppHandle,
     repo_path: String,
     remote: Option<String>,
     branch: Option<String>,
@@ -88,6 +93,7 @@ pub async fn push_remote(
     set_upstream: bool,
     push_tags: bool,
     ssh_key_path: Option<String>,
+    request_id: Option<String>,
 ) -> Result<(), String> {
     let resolved_key = resolve_ssh_key(ssh_key_path.as_deref())?;
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
@@ -99,6 +105,7 @@ pub async fn push_remote(
         set_upstream,
         push_tags,
         resolved_key.as_deref(),
+        emit_hook_output(&app, request_id),
     )
     .map_err(|e| e.to_string())
 }
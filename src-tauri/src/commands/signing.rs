@@ -0,0 +1,42 @@
+use crate::git::{
+    executor::GitExecutor,
+    signing::{self, SignOptions, SigningConfig},
+    types::SignatureFormat,
+};
+
+use super::remote::resolve_ssh_key;
+
+/// Resolve the key an SSH signature will be made with, using the session
+/// unlocked copy of an encrypted key. Returns an error starting with
+/// SSH_KEY_LOCKED: when the key needs unlocking first.
+pub(crate) fn resolve_signing(
+    executor: &GitExecutor,
+    options: Option<SignOptions>,
+    for_tag: bool,
+) -> Result<SignOptions, String> {
+    let mut options = options.unwrap_or_default();
+    let config = signing::get_signing_config(executor).map_err(|e| e.to_string())?;
+    let enabled = if for_tag { config.sign_tags } else { config.sign_commits };
+    if config.format != SignatureFormat::Ssh || !options.sign.unwrap_or(enabled) {
+        return Ok(options);
+    }
+
+    let key = options.key.clone().or(config.key);
+    let Some(private_key) = key.as_deref().and_then(signing::ssh_private_key_path) else {
+        // Signed through ssh-agent
+        return Ok(options);
+    };
+    let private_key = private_key.to_string_lossy().to_string();
+    if let Some(resolved) = resolve_ssh_key(Some(&private_key))? {
+        if resolved != private_key {
+            options.key = Some(resolved);
+        }
+    }
+    Ok(options)
+}
+
+#[tauri::command]
+pub async fn get_signing_config(repo_path: String) -> Result<SigningConfig, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    signing::get_signing_config(&executor).map_err(|e| e.to_string())
+}
@@ -0,0 +1,11 @@
+use crate::git::{
+    executor::GitExecutor,
+    stats::{self, RepositoryStats, StatsQuery},
+};
+
+/// Contributor and activity statistics over a revision range
+#[tauri::command]
+pub async fn get_repository_stats(repo_path: String, query: Option<StatsQuery>) -> Result<RepositoryStats, String> {
+    let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
+    stats::get_repository_stats(&executor, &query.unwrap_or_default()).map_err(|e| e.to_string())
+}
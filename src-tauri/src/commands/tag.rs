@@ -1,4 +1,6 @@
-use crate::git::{tag, executor::GitExecutor, tag::Tag};
+use crate::git::{tag, executor::GitExecutor, signing::SignOptions, tag::Tag};
+
+use super::signing::resolve_signing;
 
 #[tauri::command]
 pub async fn get_tags(repo_path: String) -> Result<Vec<Tag>, String> {
@@ -12,9 +14,11 @@ pub async fn create_tag(
     name: String,
     commit: Option<String>,
     message: Option<String>,
+    signing: Option<SignOptions>,
 ) -> Result<(), String> {
     let executor = GitExecutor::new(&repo_path).map_err(|e| e.to_string())?;
-    tag::create_tag(&executor, &name, commit.as_deref(), message.as_deref()).map_err(|e| e.to_string())
+    let signing = resolve_signing(&executor, signing, true)?;
+    tag::create_tag(&executor, &name, commit.as_deref(), message.as_deref(), &signing).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
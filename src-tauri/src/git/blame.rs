@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::log::unquote_path;
+use crate::git::mailmap::{get_raw_identities, RawIdentity};
+
+/// Number of ranges handed to the callback at once when streaming
+const BLAME_BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlameOptions {
+    /// Revision to blame at, the working tree when None
+    pub revision: Option<String>,
+    /// Ignore whitespace changes (`-w`)
+    pub ignore_whitespace: bool,
+    /// File listing revisions to skip (`--ignore-revs-file`)
+    pub ignore_revs_file: Option<String>,
+}
+
+/// A run of consecutive lines last changed by the same commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameRange {
+    pub hash: String,
+    /// First line of the range in the blamed file (1-based)
+    pub start_line: u32,
+    pub line_count: u32,
+    /// First line of the range in the commit that introduced it
+    pub original_start_line: u32,
+    /// Path of the file in the commit that introduced the range
+    pub original_path: String,
+    /// Identities with .mailmap applied, as git blame reports them
+    pub author_name: String,
+    pub author_email: String,
+    pub author_timestamp: i64,
+    pub author_tz: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub committer_timestamp: i64,
+    pub committer_tz: String,
+    /// Identities as recorded in the commit, before .mailmap
+    pub raw_author_name: String,
+    pub raw_author_email: String,
+    pub raw_committer_name: String,
+    pub raw_committer_email: String,
+    pub summary: String,
+    /// True for the boundary commit of the blamed range (e.g. the root commit)
+    pub boundary: bool,
+    /// True for lines not committed yet
+    pub uncommitted: bool,
+}
+
+/// Commit details, printed by git only the first time a commit shows up
+#[derive(Debug, Clone, Default)]
+struct BlameCommitInfo {
+    author_name: String,
+    author_email: String,
+    author_timestamp: i64,
+    author_tz: String,
+    committer_name: String,
+    committer_email: String,
+    committer_timestamp: i64,
+    committer_tz: String,
+    summary: String,
+    boundary: bool,
+}
+
+/// Blame a file and return all ranges ordered by line
+pub fn blame(executor: &GitExecutor, path: &str, options: &BlameOptions) -> Result<Vec<BlameRange>, GitError> {
+    let mut ranges = Vec::new();
+    blame_incremental(executor, path, options, |batch| ranges.extend(batch))?;
+    ranges.sort_by_key(|r| r.start_line);
+    Ok(ranges)
+}
+
+/// Blame a file, handing ranges to `on_ranges` in batches as git finds them.
+/// Ranges arrive in discovery order, not line order.
+pub fn blame_incremental<F>(
+    executor: &GitExecutor,
+    path: &str,
+    options: &BlameOptions,
+    mut on_ranges: F,
+) -> Result<(), GitError>
+where
+    F: FnMut(Vec<BlameRange>),
+{
+    if let Some(revision) = options.revision.as_deref().filter(|r| r.starts_with('-')) {
+        // Never let the revision be read as an option
+        return Err(GitError::RevisionNotFound {
+            revision: revision.to_string(),
+        });
+    }
+
+    let mut args = vec!["blame", "--incremental"];
+    if options.ignore_whitespace {
+        args.push("-w");
+    }
+    if let Some(ref file) = options.ignore_revs_file {
+        args.push("--ignore-revs-file");
+        args.push(file);
+    }
+    if let Some(ref revision) = options.revision {
+        args.push(revision);
+    }
+    args.push("--");
+    args.push(path);
+
+    let mut parser = BlameParser::default();
+    let mut batch = Vec::new();
+    let mut raw_identities = HashMap::new();
+
+    executor.execute_streaming(&args, |line| {
+        if let Some(range) = parser.push_line(line) {
+            batch.push(range);
+            if batch.len() >= BLAME_BATCH_SIZE {
+                fill_raw_identities(executor, &mut batch, &mut raw_identities);
+                on_ranges(std::mem::take(&mut batch));
+            }
+        }
+    })?;
+
+    if !batch.is_empty() {
+        fill_raw_identities(executor, &mut batch, &mut raw_identities);
+        on_ranges(batch);
+    }
+
+    Ok(())
+}
+
+/// Set the raw identities of ranges, looking up commits not seen before.
+/// git blame only reports mailmapped identities; when the lookup fails, or
+/// for uncommitted lines, the mailmapped values are used.
+fn fill_raw_identities(
+    executor: &GitExecutor,
+    ranges: &mut [BlameRange],
+    known: &mut HashMap<String, RawIdentity>,
+) {
+    let mut missing: Vec<String> = ranges
+        .iter()
+        .filter(|r| !r.uncommitted && !known.contains_key(&r.hash))
+        .map(|r| r.hash.clone())
+        .collect();
+    missing.sort();
+    missing.dedup();
+    if let Ok(found) = get_raw_identities(executor, &missing) {
+        known.extend(found);
+    }
+
+    for range in ranges {
+        match known.get(&range.hash) {
+            Some(raw) => {
+                range.raw_author_name = raw.author_name.clone();
+                range.raw_author_email = raw.author_email.clone();
+                range.raw_committer_name = raw.committer_name.clone();
+                range.raw_committer_email = raw.committer_email.clone();
+            }
+            None => {
+                range.raw_author_name = range.author_name.clone();
+                range.raw_author_email = range.author_email.clone();
+                range.raw_committer_name = range.committer_name.clone();
+                range.raw_committer_email = range.committer_email.clone();
+            }
+        }
+    }
+}
+
+/// Parser for `git blame --incremental` output
+#[derive(Default)]
+struct BlameParser {
+    commits: HashMap<String, BlameCommitInfo>,
+    current: Option<BlameRange>,
+}
+
+impl BlameParser {
+    /// Feed one line, returning a range once its "filename" line is reached
+    fn push_line(&mut self, line: &str) -> Option<BlameRange> {
+        if let Some(range) = parse_range_header(line) {
+            self.current = Some(range);
+            return None;
+        }
+
+        let hash = self.current.as_ref()?.hash.clone();
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+
+        if key == "filename" {
+            let mut range = self.current.take()?;
+            // Paths with special characters are C-quoted, like in diffs
+            range.original_path = unquote_path(value);
+            let info = self.commits.get(&hash).cloned().unwrap_or_default();
+            range.author_name = info.author_name;
+            range.author_email = info.author_email;
+            range.author_timestamp = info.author_timestamp;
+            range.author_tz = info.author_tz;
+            range.committer_name = info.committer_name;
+            range.committer_email = info.committer_email;
+            range.committer_timestamp = info.committer_timestamp;
+            range.committer_tz = info.committer_tz;
+            range.summary = info.summary;
+            range.boundary = info.boundary;
+            range.uncommitted = hash.chars().all(|c| c == '0');
+            return Some(range);
+        }
+
+        let info = self.commits.entry(hash).or_default();
+        match key {
+            "author" => info.author_name = value.to_string(),
+            "author-mail" => info.author_email = trim_mail(value),
+            "author-time" => info.author_timestamp = value.parse().unwrap_or(0),
+            "author-tz" => info.author_tz = value.to_string(),
+            "committer" => info.committer_name = value.to_string(),
+            "committer-mail" => info.committer_email = trim_mail(value),
+            "committer-time" => info.committer_timestamp = value.parse().unwrap_or(0),
+            "committer-tz" => info.committer_tz = value.to_string(),
+            "summary" => info.summary = value.to_string(),
+            "boundary" => info.boundary = true,
+            _ => {}
+        }
+
+        None
+    }
+}
+
+/// Parse "<hash> <original line> <final line> <line count>"
+fn parse_range_header(line: &str) -> Option<BlameRange> {
+    let parts: Vec<&str> = line.split(' ').collect();
+    if parts.len() != 4 || !(parts[0].len() == 40 || parts[0].len() == 64) {
+        return None;
+    }
+    if !parts[0].chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(BlameRange {
+        hash: parts[0].to_string(),
+        original_start_line: parts[1].parse().ok()?,
+        start_line: parts[2].parse().ok()?,
+        line_count: parts[3].parse().ok()?,
+        original_path: String::new(),
+        author_name: String::new(),
+        author_email: String::new(),
+        author_timestamp: 0,
+        author_tz: String::new(),
+        committer_name: String::new(),
+        committer_email: String::new(),
+        committer_timestamp: 0,
+        committer_tz: String::new(),
+        raw_author_name: String::new(),
+        raw_author_email: String::new(),
+        raw_committer_name: String::new(),
+        raw_committer_email: String::new(),
+        summary: String::new(),
+        boundary: false,
+        uncommitted: false,
+    })
+}
+
+/// "<jane@example.com>" -> "jane@example.com"
+fn trim_mail(value: &str) -> String {
+    value.trim_start_matches('<').trim_end_matches('>').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_incremental_output() {
+        let hash_a = "a".repeat(40);
+        let hash_b = "b".repeat(40);
+        let output = format!(
+            "{a} 1 1 2\nauthor Jane\nauthor-mail <jane@example.com>\nauthor-time 1700000000\n\
+             author-tz +0100\ncommitter Jane\ncommitter-mail <jane@example.com>\n\
+             committer-time 1700000000\ncommitter-tz +0100\nsummary First\nboundary\nfilename old.txt\n\
+             {b} 3 3 1\nauthor Joe\nauthor-mail <joe@example.com>\nauthor-time 1700000100\n\
+             author-tz +0000\ncommitter Joe\ncommitter-mail <joe@example.com>\n\
+             committer-time 1700000100\ncommitter-tz +0000\nsummary Second\n\
+             previous {a} old.txt\nfilename new.txt\n\
+             {a} 4 5 1\nfilename old.txt\n\
+             {b} 6 6 1\nfilename \"caf\\303\\251 \\\"menu\\\".txt\"\n",
+            a = hash_a,
+            b = hash_b
+        );
+
+        let mut parser = BlameParser::default();
+        let ranges: Vec<BlameRange> = output.lines().filter_map(|l| parser.push_line(l)).collect();
+
+        assert_eq!(ranges.len(), 4);
+        assert_eq!((ranges[0].start_line, ranges[0].line_count), (1, 2));
+        assert!(ranges[0].boundary);
+        assert_eq!(ranges[1].author_email, "joe@example.com");
+        assert_eq!(ranges[1].original_path, "new.txt");
+        // Details of a commit seen before are reused
+        assert_eq!(ranges[2].summary, "First");
+        assert_eq!((ranges[2].original_start_line, ranges[2].start_line), (4, 5));
+        assert_eq!(ranges[3].original_path, "café \"menu\".txt");
+    }
+
+    #[test]
+    fn rejects_revisions_that_look_like_options() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let executor = GitExecutor::new(dir.path().to_str().unwrap()).unwrap();
+        let options = BlameOptions {
+            revision: Some("--output=x".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            blame(&executor, "file.txt", &options),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+    }
+}
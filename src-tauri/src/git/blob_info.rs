@@ -0,0 +1,201 @@
+use crate::git::types::{ImageDimensions, LfsPointer};
+
+/// Number of leading bytes read from a blob to sniff its type and dimensions
+pub const SNIFF_BYTES: u64 = 64 * 1024;
+
+const LFS_VERSION_LINES: &[&str] = &[
+    "version https://git-lfs.github.com/spec/v1",
+    "version https://hawser.github.com/spec/v1",
+];
+
+/// Guess the MIME type of binary content from its magic bytes
+pub fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    let mime = match bytes {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'B', b'M', ..] if bytes.len() >= 26 => "image/bmp",
+        [0x00, 0x00, 0x01, 0x00, ..] => "image/x-icon",
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => "image/tiff",
+        [b'%', b'P', b'D', b'F', b'-', ..] => "application/pdf",
+        [b'P', b'K', 0x03, 0x04, ..] | [b'P', b'K', 0x05, 0x06, ..] => "application/zip",
+        [0x1F, 0x8B, ..] => "application/gzip",
+        [b'B', b'Z', b'h', ..] => "application/x-bzip2",
+        [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..] => "application/x-xz",
+        [0x28, 0xB5, 0x2F, 0xFD, ..] => "application/zstd",
+        [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C, ..] => "application/x-7z-compressed",
+        [b'R', b'a', b'r', b'!', 0x1A, 0x07, ..] => "application/vnd.rar",
+        [0x00, b'a', b's', b'm', ..] => "application/wasm",
+        [0x7F, b'E', b'L', b'F', ..] => "application/x-elf",
+        [b'M', b'Z', ..] => "application/vnd.microsoft.portable-executable",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "video/mp4",
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => "video/webm",
+        [b'w', b'O', b'F', b'F', ..] => "font/woff",
+        [b'w', b'O', b'F', b'2', ..] => "font/woff2",
+        [0x00, 0x01, 0x00, 0x00, ..] => "font/ttf",
+        [b'O', b'T', b'T', b'O', ..] => "font/otf",
+        [b'S', b'Q', b'L', b'i', b't', b'e', b' ', b'f', b'o', b'r', b'm', b'a', b't', ..] => {
+            "application/vnd.sqlite3"
+        }
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Read the pixel dimensions of PNG, GIF, JPEG, BMP and WebP images
+pub fn image_dimensions(bytes: &[u8]) -> Option<ImageDimensions> {
+    let (width, height) = match sniff_mime_type(bytes)? {
+        "image/png" => (be_u32(bytes, 16)?, be_u32(bytes, 20)?),
+        "image/gif" => (le_u16(bytes, 6)? as u32, le_u16(bytes, 8)? as u32),
+        "image/bmp" => {
+            let width = le_u32(bytes, 18)? as i32;
+            let height = le_u32(bytes, 22)? as i32;
+            (width.unsigned_abs(), height.unsigned_abs())
+        }
+        "image/jpeg" => jpeg_dimensions(bytes)?,
+        "image/webp" => webp_dimensions(bytes)?,
+        _ => return None,
+    };
+    Some(ImageDimensions { width, height })
+}
+
+/// Walk JPEG segments until a start-of-frame marker
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    while pos + 9 < bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = bytes[pos + 1];
+        // Fill bytes and standalone markers carry no length
+        if marker == 0xFF || marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            pos += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let length = be_u16(bytes, pos + 2)? as usize;
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let height = be_u16(bytes, pos + 5)? as u32;
+            let width = be_u16(bytes, pos + 7)? as u32;
+            return Some((width, height));
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => {
+            let width = le_u16(bytes, 26)? & 0x3FFF;
+            let height = le_u16(bytes, 28)? & 0x3FFF;
+            Some((width as u32, height as u32))
+        }
+        b"VP8L" => {
+            let bits = le_u32(bytes, 21)?;
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => {
+            let width = le_u24(bytes, 24)? + 1;
+            let height = le_u24(bytes, 27)? + 1;
+            Some((width, height))
+        }
+        _ => None,
+    }
+}
+
+/// Parse a Git LFS pointer file
+pub fn parse_lfs_pointer(text: &str) -> Option<LfsPointer> {
+    // Pointer files are tiny, anything bigger is real content
+    if text.len() > 1024 {
+        return None;
+    }
+
+    let mut lines = text.lines().map(|l| l.trim_end_matches('\r'));
+    if !LFS_VERSION_LINES.contains(&lines.next()?) {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("oid sha256:") {
+            oid = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.parse().ok();
+        }
+    }
+
+    Some(LfsPointer { oid: oid?, size: size? })
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::png;
+
+    #[test]
+    fn reads_png_dimensions() {
+        let bytes = png(640, 480);
+        assert_eq!(sniff_mime_type(&bytes), Some("image/png"));
+        let dims = image_dimensions(&bytes).unwrap();
+        assert_eq!((dims.width, dims.height), (640, 480));
+    }
+
+    #[test]
+    fn reads_gif_and_jpeg_dimensions() {
+        let gif = b"GIF89a\x20\x03\x58\x02\x00\x00\x00";
+        let dims = image_dimensions(gif).unwrap();
+        assert_eq!((dims.width, dims.height), (800, 600));
+
+        // SOI, APP0 (length 16), SOF0 with height 0x0120 and width 0x0200
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(&[0; 14]);
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x20, 0x02, 0x00, 0x03]);
+        let dims = image_dimensions(&jpeg).unwrap();
+        assert_eq!((dims.width, dims.height), (512, 288));
+    }
+
+    #[test]
+    fn unknown_content_has_no_mime_type() {
+        assert_eq!(sniff_mime_type(b"hello world"), None);
+        assert!(image_dimensions(b"PK\x03\x04").is_none());
+    }
+
+    #[test]
+    fn parses_lfs_pointer() {
+        let text = "version https://git-lfs.github.com/spec/v1\n\
+                    oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                    size 12345\n";
+        let pointer = parse_lfs_pointer(text).unwrap();
+        assert_eq!(pointer.size, 12345);
+        assert!(pointer.oid.starts_with("4d7a2146"));
+        assert!(parse_lfs_pointer("version 1\nsize 3\n").is_none());
+    }
+}
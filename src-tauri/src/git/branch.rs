@@ -1,5 +1,9 @@
+use std::collections::HashMap;
+
 use crate::git::error::GitError;
 use crate::git::executor::GitExecutor;
+use crate::git::hooks::{run_hooked, HookOutput};
+use crate::git::signing::{signing_error, SignOptions};
 use crate::git::types::Branch;
 
 pub fn get_branches(executor: &GitExecutor) -> Result<Vec<Branch>, GitError> {
@@ -119,22 +123,37 @@ pub fn delete_remote_branch(executor: &GitExecutor, remote: &str, branch: &str)
     Ok(())
 }
 
-pub fn merge_branch(
+/// Merge a branch, handing the output of git and of the hooks it runs to
+/// `on_output` as it is printed
+pub fn merge_branch<F>(
     executor: &GitExecutor,
     name: &str,
     no_ff: bool,
-) -> Result<(), GitError> {
-    let mut args = vec!["merge", name];
+    signing: &SignOptions,
+    on_output: F,
+) -> Result<(), GitError>
+where
+    F: FnMut(HookOutput),
+{
+    let config_args = signing.config_args();
+    let mut args: Vec<&str> = config_args.iter().map(|a| a.as_str()).collect();
+    args.extend(["merge", name]);
     if no_ff {
         args.push("--no-ff");
     }
+    if let Some(flag) = signing.commit_flag() {
+        args.push(flag);
+    }
 
-    let result = executor.execute(&args)?;
+    let result = run_hooked(executor, &args, &HashMap::new(), on_output)?;
 
     if result.exit_code != 0 {
         if result.stderr.contains("CONFLICT") || result.stdout.contains("CONFLICT") {
             return Err(GitError::MergeConflict);
         }
+        if let Some(error) = signing_error(&result) {
+            return Err(error);
+        }
         return Err(GitError::CommandFailed {
             code: result.exit_code,
             stderr: result.stderr,
@@ -160,6 +179,101 @@ pub fn rebase_branch(executor: &GitExecutor, onto: &str) -> Result<(), GitError>
     Ok(())
 }
 
+/// Fold the fixup!, squash! and amend! commits made after `base` into their
+/// targets. When `base` is None, only the history from the oldest target on
+/// is rewritten, and nothing is done without any fixup commit. Merges are
+/// kept. Local changes are stashed for the rebase and put back afterwards.
+/// On conflict the rebase stops and is resumed with `continue_rebase` or
+/// undone with `abort_rebase`.
+pub fn autosquash(executor: &GitExecutor, base: Option<&str>) -> Result<(), GitError> {
+    let mut args = vec!["rebase", "-i", "--autosquash", "--autostash", "--rebase-merges"];
+    let default_base;
+    match base {
+        Some(base) if base.starts_with('-') => {
+            return Err(GitError::RevisionNotFound {
+                revision: base.to_string(),
+            })
+        }
+        Some(base) => args.push(base),
+        None => {
+            default_base = match autosquash_base(executor)? {
+                Some(base) => base,
+                None => return Ok(()),
+            };
+            args.push(&default_base);
+        }
+    }
+
+    // Accept the todo list as autosquash ordered it, and the combined
+    // messages of squash! commits as they are
+    let env = HashMap::from([
+        ("GIT_SEQUENCE_EDITOR".to_string(), "true".to_string()),
+        ("GIT_EDITOR".to_string(), "true".to_string()),
+    ]);
+    let result = executor.execute_with_env(&args, &env)?;
+
+    if result.exit_code != 0 {
+        if result.stderr.contains("CONFLICT") || result.stdout.contains("CONFLICT") {
+            return Err(GitError::MergeConflict);
+        }
+        return Err(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Where `autosquash` starts by default: the parent of the oldest commit a
+/// fixup!, squash! or amend! commit of HEAD targets, or "--root" when that
+/// commit has none. None when there is no such commit.
+fn autosquash_base(executor: &GitExecutor) -> Result<Option<String>, GitError> {
+    let output = executor.execute_checked(&["log", "--format=%H%x00%P%x00%s", "HEAD"])?;
+    let commits: Vec<(&str, &str, &str)> = output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\0');
+            Some((parts.next()?, parts.next()?, parts.next()?))
+        })
+        .collect();
+
+    // Targets are matched the way rebase does: by subject, then by hash,
+    // then by the start of the subject
+    let mut oldest: Option<usize> = None;
+    for (index, (_, _, subject)) in commits.iter().enumerate() {
+        let Some(target) = fixup_target(subject) else {
+            continue;
+        };
+        let older = &commits[index + 1..];
+        let is_hash = target.len() >= 4 && target.chars().all(|c| c.is_ascii_hexdigit());
+        let found = older
+            .iter()
+            .position(|c| c.2 == target)
+            .or_else(|| older.iter().position(|c| is_hash && c.0.starts_with(target)))
+            .or_else(|| older.iter().position(|c| c.2.starts_with(target)));
+        if let Some(found) = found {
+            oldest = oldest.max(Some(index + 1 + found));
+        }
+    }
+
+    Ok(oldest.map(|index| {
+        let parent = commits[index].1.split_whitespace().next();
+        parent.unwrap_or("--root").to_string()
+    }))
+}
+
+/// "fixup! squash! Subject" -> "Subject", None for other subjects
+fn fixup_target(subject: &str) -> Option<&str> {
+    let mut target = None;
+    let mut rest = subject;
+    while let Some(stripped) = ["fixup! ", "squash! ", "amend! "].iter().find_map(|p| rest.strip_prefix(p)) {
+        rest = stripped;
+        target = Some(rest);
+    }
+    target
+}
+
 pub fn abort_merge(executor: &GitExecutor) -> Result<(), GitError> {
     executor.execute_checked(&["merge", "--abort"])?;
     Ok(())
@@ -171,7 +285,10 @@ pub fn abort_rebase(executor: &GitExecutor) -> Result<(), GitError> {
 }
 
 pub fn continue_rebase(executor: &GitExecutor) -> Result<(), GitError> {
-    executor.execute_checked(&["rebase", "--continue"])?;
+    // Keep the message of the commit being replayed, or the combined message
+    // of a squash, instead of waiting for an editor
+    let env = HashMap::from([("GIT_EDITOR".to_string(), "true".to_string())]);
+    executor.execute_with_env_checked(&["rebase", "--continue"], &env)?;
     Ok(())
 }
 
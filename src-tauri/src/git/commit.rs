@@ -1,17 +1,197 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
 use crate::git::error::GitError;
 use crate::git::executor::GitExecutor;
+use crate::git::hooks::{run_hooked, HookOutput};
+use crate::git::signing::{signing_error, SignOptions};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitOptions {
+    pub amend: bool,
+    /// When amending, keep the previous message and ignore `message` (`--no-edit`)
+    pub keep_message: bool,
+    /// When amending, make the committer the author again (`--reset-author`)
+    pub reset_author: bool,
+    /// Add a Signed-off-by trailer (`--signoff`)
+    pub signoff: bool,
+    /// Author overrides, each falling back to the configured identity
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Author date in any format git accepts
+    pub author_date: Option<String>,
+    /// Skip the pre-commit and commit-msg hooks (`--no-verify`)
+    pub no_verify: bool,
+    pub allow_empty: bool,
+    pub signing: SignOptions,
+}
 
-pub fn create_commit(
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CherryPickOptions {
+    /// Append "(cherry picked from commit ...)" to the messages (`-x`)
+    pub record_origin: bool,
+    /// Parent, from 1, whose side of merge commits is picked (`--mainline`)
+    pub mainline: Option<u32>,
+    /// Apply the changes to the index without committing (`--no-commit`)
+    pub no_commit: bool,
+    pub signing: SignOptions,
+}
+
+/// Where a sequence of cherry-picks stands
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CherryPickProgress {
+    /// Commit the sequence stopped at with conflicts, None once every
+    /// commit is picked
+    pub stopped_at: Option<String>,
+    /// Commits left to pick after `stopped_at`, in order
+    pub remaining: Vec<String>,
+}
+
+/// Kind of commit made to be folded into an earlier one by an autosquash rebase
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FixupKind {
+    /// "fixup!": keep the target's message
+    Fixup,
+    /// "squash!": append this commit's message to the target's
+    Squash,
+    /// "amend!": replace the target's message with this commit's
+    Amend,
+}
+
+/// Create a commit, handing the output of git and of the hooks it runs to
+/// `on_output` as it is printed
+pub fn create_commit<F>(
     executor: &GitExecutor,
     message: &str,
-    amend: bool,
-) -> Result<String, GitError> {
-    let mut args = vec!["commit", "-m", message];
-    if amend {
+    options: &CommitOptions,
+    on_output: F,
+) -> Result<String, GitError>
+where
+    F: FnMut(HookOutput),
+{
+    if options.amend && options.keep_message {
+        run_commit(executor, &["--no-edit"], options, on_output)
+    } else {
+        run_commit(executor, &["-m", message], options, on_output)
+    }
+}
+
+/// Create a fixup!, squash! or amend! commit of the staged changes for
+/// `target`. `message` is added to the body of fixup! and squash! commits
+/// and is the new message of the target for amend! ones.
+pub fn create_fixup_commit<F>(
+    executor: &GitExecutor,
+    target: &str,
+    kind: FixupKind,
+    message: Option<&str>,
+    options: &CommitOptions,
+    on_output: F,
+) -> Result<String, GitError>
+where
+    F: FnMut(HookOutput),
+{
+    let target = resolve_commit(executor, target)?;
+    let options = CommitOptions {
+        amend: false,
+        ..options.clone()
+    };
+
+    let message = message.filter(|m| !m.trim().is_empty());
+    let (flag, message) = match (kind, message) {
+        (FixupKind::Fixup, message) => (Some(format!("--fixup={}", target)), message.map(|m| m.to_string())),
+        (FixupKind::Squash, message) => (Some(format!("--squash={}", target)), message.map(|m| m.to_string())),
+        // git only takes the new message of an amend! commit from the editor
+        (FixupKind::Amend, Some(message)) => {
+            let subject = executor.execute_checked(&["log", "-1", "--format=%s", &target])?;
+            (None, Some(format!("amend! {}\n\n{}", subject.trim(), message)))
+        }
+        (FixupKind::Amend, None) => (Some(format!("--fixup=amend:{}", target)), None),
+    };
+
+    let mut args: Vec<&str> = flag.iter().map(|f| f.as_str()).collect();
+    if let Some(ref message) = message {
+        args.push("-m");
+        args.push(message);
+    }
+    run_commit(executor, &args, &options, on_output)
+}
+
+fn resolve_commit(executor: &GitExecutor, revision: &str) -> Result<String, GitError> {
+    let not_found = || GitError::RevisionNotFound {
+        revision: revision.to_string(),
+    };
+    if revision.starts_with('-') {
+        return Err(not_found());
+    }
+    let result = executor.execute(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", revision)])?;
+    if result.exit_code != 0 {
+        return Err(not_found());
+    }
+    Ok(result.stdout.trim().to_string())
+}
+
+fn run_commit<F>(
+    executor: &GitExecutor,
+    message_args: &[&str],
+    options: &CommitOptions,
+    on_output: F,
+) -> Result<String, GitError>
+where
+    F: FnMut(HookOutput),
+{
+    let config_args = options.signing.config_args();
+    let mut args: Vec<&str> = config_args.iter().map(|a| a.as_str()).collect();
+    args.push("commit");
+    if let Some(flag) = options.signing.commit_flag() {
+        args.push(flag);
+    }
+    args.extend_from_slice(message_args);
+    if options.amend {
         args.push("--amend");
     }
+    if options.reset_author {
+        args.push("--reset-author");
+    }
+    if options.signoff {
+        args.push("--signoff");
+    }
+    if options.no_verify {
+        args.push("--no-verify");
+    }
+    if options.allow_empty {
+        args.push("--allow-empty");
+    }
+    let date_arg;
+    if let Some(ref date) = options.author_date {
+        date_arg = format!("--date={}", date);
+        args.push(&date_arg);
+    }
+
+    // The environment lets the name and email be overridden separately,
+    // which `--author` does not
+    let mut env = HashMap::new();
+    // Messages are always given, never wait for an editor
+    env.insert("GIT_EDITOR".to_string(), "true".to_string());
+    if let Some(ref name) = options.author_name {
+        env.insert("GIT_AUTHOR_NAME".to_string(), name.clone());
+    }
+    if let Some(ref email) = options.author_email {
+        env.insert("GIT_AUTHOR_EMAIL".to_string(), email.clone());
+    }
 
-    executor.execute_checked(&args)?;
+    let result = run_hooked(executor, &args, &env, on_output)?;
+    if result.exit_code != 0 {
+        return Err(signing_error(&result).unwrap_or(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        }));
+    }
 
     // Get the new commit hash
     let hash = executor.execute_checked(&["rev-parse", "HEAD"])?;
@@ -23,20 +203,109 @@ pub fn get_last_commit_message(executor: &GitExecutor) -> Result<String, GitErro
     Ok(output.trim().to_string())
 }
 
-pub fn cherry_pick(executor: &GitExecutor, hash: &str) -> Result<(), GitError> {
-    let result = executor.execute(&["cherry-pick", hash])?;
+/// Pick a single commit, failing with MergeConflict when it does not apply
+pub fn cherry_pick(executor: &GitExecutor, hash: &str, signing: &SignOptions) -> Result<(), GitError> {
+    let options = CherryPickOptions {
+        signing: signing.clone(),
+        ..Default::default()
+    };
+    let progress = cherry_pick_commits(executor, &[hash.to_string()], &options)?;
+    if progress.stopped_at.is_some() {
+        return Err(GitError::MergeConflict);
+    }
+    Ok(())
+}
 
-    if result.exit_code != 0 {
-        if result.stderr.contains("CONFLICT") || result.stdout.contains("CONFLICT") {
-            return Err(GitError::MergeConflict);
+/// Pick commits and ranges ("A..B", oldest first) in the order given.
+/// Stopping on a conflict is not an error: the returned progress tells
+/// which commit stopped the sequence, to be resolved and continued, skipped
+/// or aborted.
+pub fn cherry_pick_commits(
+    executor: &GitExecutor,
+    revisions: &[String],
+    options: &CherryPickOptions,
+) -> Result<CherryPickProgress, GitError> {
+    let mut commits: Vec<String> = Vec::new();
+    for revision in revisions {
+        if revision.contains("..") && !revision.starts_with('-') {
+            let output = executor.execute_checked(&["rev-list", "--reverse", revision, "--"])?;
+            commits.extend(output.lines().map(|l| l.to_string()));
+        } else {
+            commits.push(resolve_commit(executor, revision)?);
         }
-        return Err(GitError::CommandFailed {
-            code: result.exit_code,
-            stderr: result.stderr,
-        });
+    }
+    if commits.is_empty() {
+        return Ok(CherryPickProgress::default());
     }
 
-    Ok(())
+    let config_args = options.signing.config_args();
+    let mut args: Vec<&str> = config_args.iter().map(|a| a.as_str()).collect();
+    args.push("cherry-pick");
+    if let Some(flag) = options.signing.commit_flag() {
+        args.push(flag);
+    }
+    if options.record_origin {
+        args.push("-x");
+    }
+    let mainline;
+    if let Some(parent) = options.mainline {
+        mainline = parent.to_string();
+        args.push("--mainline");
+        args.push(&mainline);
+    }
+    if options.no_commit {
+        args.push("--no-commit");
+    }
+    args.extend(commits.iter().map(|c| c.as_str()));
+
+    let result = executor.execute(&args)?;
+    if result.exit_code == 0 {
+        return Ok(CherryPickProgress::default());
+    }
+    if result.stderr.contains("CONFLICT") || result.stdout.contains("CONFLICT") {
+        let mut progress = cherry_pick_progress(executor)?;
+        // A single commit picked with --no-commit leaves no state behind
+        if progress.stopped_at.is_none() {
+            progress.stopped_at = Some(commits[0].clone());
+        }
+        return Ok(progress);
+    }
+    if let Some(error) = signing_error(&result) {
+        return Err(error);
+    }
+    Err(GitError::CommandFailed {
+        code: result.exit_code,
+        stderr: result.stderr,
+    })
+}
+
+/// Where the cherry-pick in progress stands, read from CHERRY_PICK_HEAD and
+/// the sequencer's todo list
+fn cherry_pick_progress(executor: &GitExecutor) -> Result<CherryPickProgress, GitError> {
+    let head = executor.execute(&["rev-parse", "--verify", "--quiet", "CHERRY_PICK_HEAD"])?;
+    let stopped_at = (head.exit_code == 0).then(|| head.stdout.trim().to_string());
+
+    let todo_path = executor.execute_checked(&["rev-parse", "--git-path", "sequencer/todo"])?;
+    let todo = fs::read_to_string(Path::new(executor.repo_path()).join(todo_path.trim())).unwrap_or_default();
+    let abbreviated: Vec<&str> = todo
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_whitespace().nth(1))
+        .collect();
+    let mut todo: Vec<String> = Vec::new();
+    if !abbreviated.is_empty() {
+        let mut args = vec!["rev-parse"];
+        args.extend(abbreviated);
+        todo = executor.execute_checked(&args)?.lines().map(|l| l.to_string()).collect();
+    }
+
+    // The first entry of the todo list is the commit being picked
+    let mut todo = todo.into_iter();
+    let current = todo.next();
+    Ok(CherryPickProgress {
+        stopped_at: stopped_at.or(current),
+        remaining: todo.collect(),
+    })
 }
 
 pub fn revert_commit(executor: &GitExecutor, hash: &str) -> Result<(), GitError> {
@@ -65,12 +334,241 @@ pub fn abort_revert(executor: &GitExecutor) -> Result<(), GitError> {
     Ok(())
 }
 
-pub fn continue_cherry_pick(executor: &GitExecutor) -> Result<(), GitError> {
-    executor.execute_checked(&["cherry-pick", "--continue"])?;
-    Ok(())
+/// Commit the resolved pick and go on with the sequence
+pub fn continue_cherry_pick(executor: &GitExecutor) -> Result<CherryPickProgress, GitError> {
+    resume_cherry_pick(executor, "--continue")
+}
+
+/// Drop the commit that stopped the sequence and go on with the next ones
+pub fn skip_cherry_pick(executor: &GitExecutor) -> Result<CherryPickProgress, GitError> {
+    resume_cherry_pick(executor, "--skip")
+}
+
+fn resume_cherry_pick(executor: &GitExecutor, action: &str) -> Result<CherryPickProgress, GitError> {
+    // Keep the message of the picked commit instead of waiting for an editor
+    let env = HashMap::from([("GIT_EDITOR".to_string(), "true".to_string())]);
+    let result = executor.execute_with_env(&["cherry-pick", action], &env)?;
+    if result.exit_code == 0 {
+        return Ok(CherryPickProgress::default());
+    }
+    if result.stderr.contains("CONFLICT") || result.stdout.contains("CONFLICT") {
+        return cherry_pick_progress(executor);
+    }
+    Err(GitError::CommandFailed {
+        code: result.exit_code,
+        stderr: result.stderr,
+    })
 }
 
 pub fn continue_revert(executor: &GitExecutor) -> Result<(), GitError> {
     executor.execute_checked(&["revert", "--continue"])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use crate::git::branch::{abort_rebase, autosquash};
+
+    #[test]
+    fn applies_commit_options() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+
+        let options = CommitOptions {
+            allow_empty: true,
+            signoff: true,
+            author_name: Some("Jane Doe".into()),
+            author_date: Some("2024-02-03T04:05:06+00:00".into()),
+            ..Default::default()
+        };
+        create_commit(&executor, "first", &options, |_| {}).unwrap();
+        let show = |format: &str| git(&["log", "-1", &format!("--format={}", format)]).trim().to_string();
+        assert_eq!(show("%an <%ae> %aI"), "Jane Doe <test@example.com> 2024-02-03T04:05:06+00:00");
+        assert_eq!(show("%B"), "first\n\nSigned-off-by: Grist Test <test@example.com>");
+
+        // Amending keeps the message and can give the commit back to the committer
+        let amend = CommitOptions {
+            amend: true,
+            keep_message: true,
+            reset_author: true,
+            allow_empty: true,
+            ..Default::default()
+        };
+        create_commit(&executor, "", &amend, |_| {}).unwrap();
+        assert_eq!(show("%an %s"), "Grist Test first");
+
+        // A failing pre-commit hook only runs without no_verify
+        let hook = git(&["rev-parse", "--git-path", "hooks/pre-commit"]);
+        let hook = dir.path().join(hook.trim());
+        std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let empty = CommitOptions {
+            allow_empty: true,
+            ..Default::default()
+        };
+        assert!(create_commit(&executor, "second", &empty, |_| {}).is_err());
+        create_commit(&executor, "second", &CommitOptions { no_verify: true, ..empty }, |_| {}).unwrap();
+        assert_eq!(show("%s"), "second");
+        assert!(create_commit(&executor, "third", &CommitOptions::default(), |_| {}).is_err());
+    }
+
+    #[test]
+    fn creates_fixups_and_autosquashes_them() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let write = |file: &str, content: &str| {
+            std::fs::write(dir.path().join(file), content).unwrap();
+            git(&["add", file]);
+        };
+        write("a.txt", "a1\n");
+        git(&["commit", "-q", "-m", "first"]);
+        write("b.txt", "b1\n");
+        git(&["commit", "-q", "-m", "second"]);
+        let first = git(&["rev-parse", "HEAD~1"]).trim().to_string();
+
+        let options = CommitOptions::default();
+        write("a.txt", "a2\n");
+        create_fixup_commit(&executor, &first, FixupKind::Fixup, None, &options, |_| {}).unwrap();
+        write("b.txt", "b2\n");
+        create_fixup_commit(&executor, "HEAD~1", FixupKind::Squash, Some("more"), &options, |_| {}).unwrap();
+        let empty = CommitOptions {
+            allow_empty: true,
+            ..Default::default()
+        };
+        create_fixup_commit(&executor, &first, FixupKind::Amend, Some("renamed"), &empty, |_| {}).unwrap();
+        let subjects = || git(&["log", "--format=%s"]);
+        assert_eq!(subjects(), "amend! first\nsquash! second\nfixup! first\nsecond\nfirst\n");
+        assert!(matches!(
+            create_fixup_commit(&executor, "nope", FixupKind::Fixup, None, &options, |_| {}),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+
+        // Unstaged changes survive the rebase
+        std::fs::write(dir.path().join("b.txt"), "local\n").unwrap();
+        autosquash(&executor, None).unwrap();
+        assert_eq!(git(&["log", "--format=%B"]), "second\n\nmore\n\nrenamed\n\n");
+        assert_eq!(git(&["show", "HEAD~1:a.txt"]), "a2\n");
+        assert_eq!(std::fs::read_to_string(dir.path().join("b.txt")).unwrap(), "local\n");
+
+        // A fixup that no longer applies to its target stops the rebase
+        git(&["checkout", "--", "b.txt"]);
+        write("a.txt", "a3\n");
+        git(&["commit", "-q", "-m", "third"]);
+        write("a.txt", "a4\n");
+        create_fixup_commit(&executor, "HEAD~2", FixupKind::Fixup, None, &options, |_| {}).unwrap();
+        assert!(matches!(autosquash(&executor, None), Err(GitError::MergeConflict)));
+        abort_rebase(&executor).unwrap();
+        assert_eq!(subjects().lines().next(), Some("fixup! renamed"));
+    }
+
+    #[test]
+    fn autosquash_keeps_merges_and_older_history() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let commit = |file: &str, content: &str, message: &str| {
+            std::fs::write(dir.path().join(file), content).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", message]);
+        };
+        commit("base.txt", "base\n", "base");
+        let base = git(&["rev-parse", "HEAD"]).trim().to_string();
+        commit("a.txt", "a1\n", "first");
+        git(&["checkout", "-q", "-b", "side"]);
+        commit("side.txt", "side\n", "side");
+        git(&["checkout", "-q", "main"]);
+        commit("b.txt", "b1\n", "second");
+        git(&["merge", "-q", "--no-ff", "-m", "merge side", "side"]);
+
+        // Nothing to squash leaves the history alone
+        let head = git(&["rev-parse", "HEAD"]);
+        autosquash(&executor, None).unwrap();
+        assert_eq!(git(&["rev-parse", "HEAD"]), head);
+
+        std::fs::write(dir.path().join("a.txt"), "a2\n").unwrap();
+        git(&["add", "a.txt"]);
+        create_fixup_commit(&executor, "HEAD~2", FixupKind::Fixup, None, &CommitOptions::default(), |_| {}).unwrap();
+        autosquash(&executor, None).unwrap();
+
+        // Rewritten from "first" on, with the merge kept
+        assert_eq!(git(&["rev-list", "--max-parents=0", "HEAD"]).trim(), base);
+        assert_eq!(git(&["log", "--first-parent", "--format=%s"]), "merge side\nsecond\nfirst\nbase\n");
+        assert_eq!(git(&["rev-list", "--merges", "--count", "HEAD"]).trim(), "1");
+        assert_eq!(git(&["show", "HEAD:a.txt"]), "a2\n");
+        assert_eq!(git(&["show", "HEAD:side.txt"]), "side\n");
+    }
+
+    #[test]
+    fn picks_sequences_and_reports_where_they_stop() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let commit = |file: &str, content: &str, message: &str| {
+            std::fs::write(dir.path().join(file), content).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", message]);
+            git(&["rev-parse", "HEAD"]).trim().to_string()
+        };
+        let base = commit("f.txt", "base\n", "base");
+        git(&["checkout", "-q", "-b", "topic"]);
+        let one = commit("1.txt", "1\n", "one");
+        commit("2.txt", "2\n", "two");
+        let three = commit("3.txt", "3\n", "three");
+        let clash = commit("f.txt", "topic\n", "clash");
+        let four = commit("4.txt", "4\n", "four");
+        git(&["checkout", "-q", "main"]);
+        commit("f.txt", "main\n", "main");
+        let subjects = |count: &str| git(&["log", "--format=%s", "-n", count]);
+
+        // Ranges are picked oldest first, in the order the arguments are given
+        let options = CherryPickOptions {
+            record_origin: true,
+            ..Default::default()
+        };
+        let revisions = vec![three.clone(), format!("{}..{}", base, one), format!("{}~1", three)];
+        let progress = cherry_pick_commits(&executor, &revisions, &options).unwrap();
+        assert_eq!(progress, CherryPickProgress::default());
+        assert_eq!(subjects("3"), "two\none\nthree\n");
+        let origin = git(&["rev-parse", &format!("{}~1", three)]);
+        assert!(git(&["log", "-1", "--format=%b"]).contains(&format!("(cherry picked from commit {})", origin.trim())));
+
+        let progress = cherry_pick_commits(&executor, &[clash.clone(), four.clone()], &options).unwrap();
+        assert_eq!(progress.stopped_at.as_deref(), Some(clash.as_str()));
+        assert_eq!(progress.remaining, vec![four.clone()]);
+        assert_eq!(skip_cherry_pick(&executor).unwrap(), CherryPickProgress::default());
+        assert_eq!(subjects("2"), "four\ntwo\n");
+
+        // Merges need a mainline, and --no-commit only stages
+        git(&["checkout", "-q", "-b", "side", &base]);
+        commit("s.txt", "s\n", "side");
+        git(&["checkout", "-q", "-b", "merged", &base]);
+        commit("m.txt", "m\n", "merged");
+        git(&["merge", "-q", "--no-ff", "-m", "merge side", "side"]);
+        git(&["checkout", "-q", "main"]);
+        let merge = vec!["merged".to_string()];
+        assert!(matches!(
+            cherry_pick_commits(&executor, &merge, &CherryPickOptions::default()),
+            Err(GitError::CommandFailed { .. })
+        ));
+        let options = CherryPickOptions {
+            mainline: Some(1),
+            no_commit: true,
+            ..Default::default()
+        };
+        assert_eq!(cherry_pick_commits(&executor, &merge, &options).unwrap(), CherryPickProgress::default());
+        assert_eq!(git(&["diff", "--cached", "--name-only"]), "s.txt\n");
+        assert_eq!(subjects("1"), "four\n");
+
+        // A single pick still fails with MergeConflict
+        git(&["reset", "-q", "--hard"]);
+        assert!(matches!(
+            cherry_pick(&executor, &clash, &SignOptions::default()),
+            Err(GitError::MergeConflict)
+        ));
+        abort_cherry_pick(&executor).unwrap();
+    }
+}
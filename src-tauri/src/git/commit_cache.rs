@@ -0,0 +1,468 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::log::get_commits;
+use crate::git::mailmap::mailmap_fingerprint;
+use crate::git::types::Commit;
+
+/// Commits fetched from git per `git log` call when filling the cache
+const FETCH_BATCH_SIZE: usize = 2000;
+
+/// Bumped when the stored Commit records change, dropping older caches
+const CACHE_FORMAT: u32 = 2;
+
+/// Repositories whose parsed commits are kept in memory at once
+const MAX_CACHED_REPOSITORIES: usize = 8;
+
+/// Parsed commits of recently used repositories, read from disk once per
+/// session and keyed by cache directory, most recently used last.
+/// Each cache has its own lock so git runs for one repository at a time
+/// without holding up the others.
+static COMMIT_CACHES: Mutex<Vec<(PathBuf, CacheSlot)>> = Mutex::new(Vec::new());
+
+/// The cache of one repository, loaded from disk on first use
+type CacheSlot = Arc<Mutex<Option<CommitCache>>>;
+
+/// On-disk cache of the `git log --all --topo-order` history.
+///
+/// Commit records never change for a given oid, so they are appended to
+/// `records.jsonl` once. The ordering is kept in `order.json` along with the
+/// ref tips and a fingerprint of the refs it was computed for. When refs
+/// move, commits new since the old tips are put in front of the order and
+/// the ones no longer reachable are dropped from it. The result is
+/// topological, though not always the order a fresh `rev-list` would give.
+/// Records hold identities with the mailmap applied, so they are all dropped
+/// when a mailmap source changes.
+#[derive(Default)]
+pub struct CommitCache {
+    records: HashMap<String, Commit>,
+    order: Vec<String>,
+    tips: Vec<String>,
+    refs_fingerprint: String,
+    mailmap_fingerprint: String,
+    /// Ref decorations by commit, as `%D` prints them with --decorate=full
+    decorations: HashMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OrderFile {
+    #[serde(default)]
+    format: u32,
+    refs_fingerprint: String,
+    #[serde(default)]
+    mailmap_fingerprint: String,
+    #[serde(default)]
+    tips: Vec<String>,
+    order: Vec<String>,
+}
+
+/// Commits the refs point to, with their decorations
+struct RefTips {
+    fingerprint: String,
+    tips: Vec<String>,
+    decorations: HashMap<String, Vec<String>>,
+}
+
+/// Directory of the commit cache of a repository
+pub fn commit_cache_dir(app_data_dir: &Path, repo_path: &str) -> PathBuf {
+    let repo_hash = format!("{:x}", md5::compute(repo_path));
+    app_data_dir.join("repositories").join(repo_hash).join("commits")
+}
+
+/// Bring the cache of a repository up to date with its refs and read from it
+pub fn with_commit_cache<T, F>(executor: &GitExecutor, cache_dir: &Path, f: F) -> Result<T, GitError>
+where
+    F: FnOnce(&CommitCache) -> T,
+{
+    let slot = cache_slot(cache_dir);
+    let mut cache = slot.lock().unwrap_or_else(|poisoned| {
+        // A panic left the cache halfway through an update, start over from disk
+        slot.clear_poison();
+        let mut cache = poisoned.into_inner();
+        *cache = None;
+        cache
+    });
+    let cache = cache.get_or_insert_with(|| CommitCache::load(cache_dir));
+
+    cache.refresh(executor, cache_dir)?;
+    Ok(f(cache))
+}
+
+/// The in-memory cache of a repository, dropping the least recently used
+/// one when there are too many
+fn cache_slot(cache_dir: &Path) -> CacheSlot {
+    let mut caches = COMMIT_CACHES.lock().unwrap_or_else(PoisonError::into_inner);
+    let slot = match caches.iter().position(|(dir, _)| dir == cache_dir) {
+        Some(index) => caches.remove(index).1,
+        None => Arc::default(),
+    };
+    caches.push((cache_dir.to_path_buf(), slot.clone()));
+    if caches.len() > MAX_CACHED_REPOSITORIES {
+        caches.remove(0);
+    }
+    slot
+}
+
+/// Same as `log::get_commit_log` with the default query, read from the cache
+pub fn get_cached_commit_log(
+    executor: &GitExecutor,
+    cache_dir: &Path,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<Commit>, GitError> {
+    with_commit_cache(executor, cache_dir, |cache| cache.page(count, skip))
+}
+
+impl CommitCache {
+    fn load(cache_dir: &Path) -> Self {
+        let mut cache = CommitCache::default();
+
+        let order = fs::read_to_string(cache_dir.join("order.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<OrderFile>(&content).ok());
+        match order {
+            Some(order) if order.format == CACHE_FORMAT => {
+                cache.order = order.order;
+                cache.tips = order.tips;
+                cache.refs_fingerprint = order.refs_fingerprint;
+                cache.mailmap_fingerprint = order.mailmap_fingerprint;
+            }
+            _ => {
+                let _ = fs::remove_file(cache_dir.join("records.jsonl"));
+                return cache;
+            }
+        }
+
+        // A partly written last line is skipped, its commit is fetched again
+        if let Ok(content) = fs::read_to_string(cache_dir.join("records.jsonl")) {
+            for line in content.lines() {
+                if let Ok(commit) = serde_json::from_str::<Commit>(line) {
+                    cache.records.insert(commit.hash.clone(), commit);
+                }
+            }
+        }
+
+        cache
+    }
+
+    /// Whether every commit of the order has its record
+    fn is_complete(&self) -> bool {
+        self.order.iter().all(|hash| self.records.contains_key(hash))
+    }
+
+    fn refresh(&mut self, executor: &GitExecutor, cache_dir: &Path) -> Result<(), GitError> {
+        let refs = read_refs(executor)?;
+        self.decorations = refs.decorations;
+
+        let mailmap = mailmap_fingerprint(executor)?;
+        if mailmap != self.mailmap_fingerprint {
+            self.records.clear();
+            let _ = fs::remove_file(cache_dir.join("records.jsonl"));
+            self.mailmap_fingerprint = mailmap;
+        }
+
+        // Never serve an order whose commits are not all known, rebuild it
+        if !self.is_complete() {
+            self.order.clear();
+            self.tips.clear();
+            self.refs_fingerprint.clear();
+        }
+        if refs.fingerprint == self.refs_fingerprint {
+            return Ok(());
+        }
+
+        let order = match self.updated_order(executor) {
+            Some(order) => order,
+            None => executor
+                .execute_checked(&["rev-list", "--all", "--topo-order"])?
+                .lines()
+                .map(|l| l.to_string())
+                .collect(),
+        };
+
+        let missing: Vec<String> = order
+            .iter()
+            .filter(|hash| !self.records.contains_key(*hash))
+            .cloned()
+            .collect();
+        for batch in missing.chunks(FETCH_BATCH_SIZE) {
+            let commits = get_commits(executor, batch)?;
+            append_records(cache_dir, &commits)?;
+            for mut commit in commits {
+                commit.refs.clear();
+                self.records.insert(commit.hash.clone(), commit);
+            }
+        }
+
+        self.order = order;
+        if !self.is_complete() {
+            self.order.clear();
+            return Err(GitError::ParseError {
+                message: "git log did not return every commit of the history".to_string(),
+            });
+        }
+        self.tips = refs.tips;
+        self.refs_fingerprint = refs.fingerprint;
+
+        // Drop commits no longer reachable once they make up most of the file
+        if self.records.len() > self.order.len() * 2 + 1000 {
+            let reachable: HashSet<&String> = self.order.iter().collect();
+            self.records.retain(|hash, _| reachable.contains(hash));
+            let records: Vec<Commit> = self.commits().cloned().collect();
+            let _ = fs::remove_file(cache_dir.join("records.jsonl"));
+            append_records(cache_dir, &records)?;
+        }
+
+        self.write_order(cache_dir)
+    }
+
+    /// The order for the new ref tips, worked out from the commits added
+    /// and removed since the old ones. None when there is nothing to start
+    /// from or git cannot tell, e.g. once an old tip was garbage collected.
+    fn updated_order(&self, executor: &GitExecutor) -> Option<Vec<String>> {
+        if self.tips.is_empty() || self.order.is_empty() {
+            return None;
+        }
+
+        let excluded: String = self.tips.iter().map(|tip| format!("^{}\n", tip)).collect();
+        let added = executor
+            .execute_with_stdin(&["rev-list", "--topo-order", "--all", "--stdin"], &excluded)
+            .ok()?;
+        let old_tips: String = self.tips.iter().map(|tip| format!("{}\n", tip)).collect();
+        let removed = executor
+            .execute_with_stdin(&["rev-list", "--not", "--all", "--stdin"], &old_tips)
+            .ok()?;
+        let removed: HashSet<&str> = removed.lines().collect();
+        // New commits are never ancestors of old ones, so they come first
+        let mut order: Vec<String> = added.lines().map(|l| l.to_string()).collect();
+        order.extend(self.order.iter().filter(|hash| !removed.contains(hash.as_str())).cloned());
+        Some(order)
+    }
+
+    fn write_order(&self, cache_dir: &Path) -> Result<(), GitError> {
+        let io_error = |e: std::io::Error| GitError::IoError {
+            message: format!("Failed to write commit cache: {}", e),
+        };
+
+        let content = serde_json::to_string(&OrderFile {
+            format: CACHE_FORMAT,
+            refs_fingerprint: self.refs_fingerprint.clone(),
+            mailmap_fingerprint: self.mailmap_fingerprint.clone(),
+            tips: self.tips.clone(),
+            order: self.order.clone(),
+        })
+        .map_err(|e| GitError::IoError { message: e.to_string() })?;
+
+        // Write then rename so a crash never leaves a truncated order file
+        fs::create_dir_all(cache_dir).map_err(io_error)?;
+        let tmp_path = cache_dir.join("order.json.tmp");
+        fs::write(&tmp_path, content).map_err(io_error)?;
+        fs::rename(&tmp_path, cache_dir.join("order.json")).map_err(io_error)
+    }
+
+    /// A page of the history, decorated with the current refs
+    pub fn page(&self, count: u32, skip: u32) -> Vec<Commit> {
+        self.hashes(count, skip)
+            .iter()
+            .filter_map(|hash| self.get(hash))
+            .collect()
+    }
+
+    /// A commit of the history, decorated with the current refs
+    pub fn get(&self, hash: &str) -> Option<Commit> {
+        let mut commit = self.records.get(hash)?.clone();
+        commit.refs = self.decorations.get(hash).cloned().unwrap_or_default();
+        Some(commit)
+    }
+
+    /// Hashes and parents of a page of the history
+    pub fn parents(&self, count: u32, skip: u32) -> Vec<(String, Vec<String>)> {
+        self.hashes(count, skip)
+            .iter()
+            .filter_map(|hash| Some((hash.clone(), self.records.get(hash)?.parent_hashes.clone())))
+            .collect()
+    }
+
+    /// Position of a commit in the history
+    pub fn position(&self, hash: &str) -> Option<usize> {
+        self.order.iter().position(|h| h == hash)
+    }
+
+    /// All commits in history order, without decorations
+    pub fn commits(&self) -> impl Iterator<Item = &Commit> {
+        self.order.iter().filter_map(|hash| self.records.get(hash))
+    }
+
+    fn hashes(&self, count: u32, skip: u32) -> &[String] {
+        let start = (skip as usize).min(self.order.len());
+        let end = start.saturating_add(count as usize).min(self.order.len());
+        &self.order[start..end]
+    }
+}
+
+/// Fingerprint the refs and read the decorations of the commits they point
+/// to, from the same `%D` the log prints
+fn read_refs(executor: &GitExecutor) -> Result<RefTips, GitError> {
+    let output = executor.execute_checked(&[
+        "log",
+        "--all",
+        "--no-walk",
+        "--decorate=full",
+        "--format=%H%x00%D",
+    ])?;
+
+    let mut tips = Vec::new();
+    let mut decorations = HashMap::new();
+    for line in output.lines() {
+        let (hash, refs) = line.split_once('\0').unwrap_or((line, ""));
+        tips.push(hash.to_string());
+        let refs: Vec<String> = refs
+            .split(", ")
+            .filter(|s| !s.is_empty())
+            .map(|s| s.trim().to_string())
+            .collect();
+        if !refs.is_empty() {
+            decorations.insert(hash.to_string(), refs);
+        }
+    }
+    tips.sort();
+
+    Ok(RefTips {
+        fingerprint: format!("{:x}", md5::compute(&output)),
+        tips,
+        decorations,
+    })
+}
+
+fn append_records(cache_dir: &Path, commits: &[Commit]) -> Result<(), GitError> {
+    let io_error = |e: std::io::Error| GitError::IoError {
+        message: format!("Failed to write commit cache: {}", e),
+    };
+
+    fs::create_dir_all(cache_dir).map_err(io_error)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(cache_dir.join("records.jsonl"))
+        .map_err(io_error)?;
+
+    let mut content = String::new();
+    for commit in commits {
+        let record = Commit {
+            refs: Vec::new(),
+            ..commit.clone()
+        };
+        if let Ok(line) = serde_json::to_string(&record) {
+            content.push_str(&line);
+            content.push('\n');
+        }
+    }
+    file.write_all(content.as_bytes()).map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::log::{get_commit_log, LogQuery};
+    use crate::git::test_util::init_repo_at;
+    use tempfile::TempDir;
+
+    #[test]
+    fn cache_matches_git_and_follows_ref_changes() {
+        let dir = TempDir::new().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let executor = init_repo_at(&dir.path().join("repo"));
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["tag", "-a", "v1", "-m", "release"]);
+        git(&["checkout", "-q", "-b", "topic"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+        git(&["checkout", "-q", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "third"]);
+
+        let summary = |commits: Vec<Commit>| -> Vec<(String, Vec<String>)> {
+            commits.into_iter().map(|c| (c.hash, c.refs)).collect()
+        };
+        let expected = summary(get_commit_log(&executor, &LogQuery::default(), 10, 0, false).unwrap());
+        let cached = summary(get_cached_commit_log(&executor, &cache_dir, 10, 0).unwrap());
+        assert_eq!(cached, expected);
+
+        let graph = crate::git::graph::get_cached_commit_graph(&executor, &cache_dir, 2, 1, None).unwrap();
+        let expected = crate::git::graph::get_commit_graph(&executor, &LogQuery::default(), 2, 1, None).unwrap();
+        let lanes = |page: &crate::git::graph::GraphPage| page.rows.iter().map(|r| r.lane).collect::<Vec<_>>();
+        assert_eq!(lanes(&graph), lanes(&expected));
+        let found = crate::git::search::search_cached_commits(&executor, &cache_dir, "scnd", 5).unwrap();
+        assert_eq!(found[0].refs, vec!["refs/heads/topic".to_string()]);
+
+        let record_lines = || fs::read_to_string(cache_dir.join("records.jsonl")).unwrap().lines().count();
+        assert_eq!(record_lines(), 3);
+
+        // Only the new commit is fetched after HEAD moves
+        git(&["commit", "-q", "--allow-empty", "-m", "fourth"]);
+        let page = get_cached_commit_log(&executor, &cache_dir, 2, 0).unwrap();
+        assert_eq!(page[0].subject, "fourth");
+        assert_eq!(page[0].refs, vec!["HEAD -> refs/heads/main".to_string()]);
+        assert_eq!(record_lines(), 4);
+
+        // A fresh session reads the records back from disk
+        let reloaded = CommitCache::load(&cache_dir);
+        assert_eq!(reloaded.commits().count(), 4);
+        assert_eq!(reloaded.commits().next().unwrap().subject, "fourth");
+
+        // Moving refs updates the order from the old tips
+        git(&["reset", "-q", "--hard", "HEAD~1"]);
+        git(&["branch", "-D", "topic"]);
+        git(&["checkout", "-q", "-b", "other", "v1"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "fifth"]);
+        let cached: Vec<String> = get_cached_commit_log(&executor, &cache_dir, 10, 0)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.hash)
+            .collect();
+        let mut expected = git(&["rev-list", "--all"]).lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert_eq!(cached[0], git(&["rev-parse", "HEAD"]).trim());
+        let mut sorted = cached.clone();
+        sorted.sort();
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn missing_records_and_poisoning_rebuild_the_cache() {
+        let dir = TempDir::new().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let executor = init_repo_at(&dir.path().join("repo"));
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+
+        let head = git(&["rev-parse", "HEAD"]).trim().to_string();
+        let panicked = std::panic::catch_unwind(|| {
+            with_commit_cache(&executor, &cache_dir, |cache| {
+                assert_eq!(cache.parents(10, 0).len(), 2);
+                panic!("reader failed");
+            })
+        });
+        assert!(panicked.is_err());
+
+        let rebuilt = with_commit_cache(&executor, &cache_dir, |cache| cache.page(10, 0).len()).unwrap();
+        assert_eq!(rebuilt, 2);
+
+        // A record lost from disk is fetched again instead of panicking
+        let records = fs::read_to_string(cache_dir.join("records.jsonl")).unwrap();
+        let kept: String = records.lines().filter(|l| !l.contains(&head)).map(|l| format!("{}\n", l)).collect();
+        fs::write(cache_dir.join("records.jsonl"), kept).unwrap();
+        let mut cache = CommitCache::load(&cache_dir);
+        assert_eq!(cache.parents(10, 0).len(), 1);
+        cache.refresh(&executor, &cache_dir).unwrap();
+        assert_eq!(cache.parents(10, 0).len(), 2);
+        assert_eq!(cache.get(&head).unwrap().refs, vec!["HEAD -> refs/heads/main".to_string()]);
+    }
+}
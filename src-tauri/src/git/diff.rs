@@ -1,47 +1,164 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
+use crate::git::blob_info::{image_dimensions, parse_lfs_pointer, sniff_mime_type, SNIFF_BYTES};
 use crate::git::error::GitError;
 use crate::git::executor::GitExecutor;
+use crate::git::partial_patch::generate_partial_patch;
+use crate::git::submodule::{detect_submodule_change, fill_submodule_commits};
 use crate::git::types::*;
 
+/// Untracked files larger than this are previewed from their first bytes only
+const MAX_UNTRACKED_PREVIEW_BYTES: u64 = 1024 * 1024;
+
+/// Number of leading bytes searched for a NUL byte, same heuristic as git
+const BINARY_SNIFF_BYTES: usize = 8000;
+
 /// Get diff for an untracked file (show entire content as additions)
 pub fn get_untracked_file_diff(executor: &GitExecutor, path: &str) -> Result<FileDiff, GitError> {
     let full_path = Path::new(executor.repo_path()).join(path);
+    let io_error = |e: std::io::Error| GitError::IoError {
+        message: format!("Failed to read file {}: {}", path, e),
+    };
+
+    let metadata = fs::symlink_metadata(&full_path).map_err(io_error)?;
+    let is_symlink = metadata.file_type().is_symlink();
+
+    // Git stores a symlink as a blob containing its target, show it the same way
+    let (bytes, size) = if is_symlink {
+        let target = fs::read_link(&full_path).map_err(io_error)?;
+        let target = target.to_string_lossy().replace('\\', "/").into_bytes();
+        let size = target.len() as u64;
+        (target, size)
+    } else {
+        let mut bytes = Vec::new();
+        fs::File::open(&full_path)
+            .and_then(|file| file.take(MAX_UNTRACKED_PREVIEW_BYTES).read_to_end(&mut bytes))
+            .map_err(io_error)?;
+        (bytes, metadata.len())
+    };
+    let truncated = size > bytes.len() as u64;
+
+    let mut diff = empty_file_diff(path, FileStatus::Untracked);
+    diff.new_size = Some(size);
+    diff.truncated = truncated;
 
-    let content = fs::read_to_string(&full_path).map_err(|e| {
-        GitError::IoError { message: format!("Failed to read file {}: {}", path, e) }
-    })?;
+    if !is_symlink && (is_binary_by_attributes(executor, path) || looks_binary(&bytes)) {
+        diff.is_binary = true;
+        diff.mime_type = sniff_mime_type(&bytes).map(str::to_string);
+        diff.new_dimensions = image_dimensions(&bytes);
+        finish_diff(&mut diff);
+        return Ok(diff);
+    }
+
+    // Only preview complete lines of a truncated file
+    let bytes = if truncated {
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            Some(pos) => &bytes[..=pos],
+            None => &bytes[..],
+        }
+    } else {
+        &bytes[..]
+    };
+    let content = decode_text(bytes);
 
     let lines: Vec<DiffLine> = content
-        .lines()
+        .split_inclusive('\n')
         .enumerate()
-        .map(|(i, line): (usize, &str)| DiffLine {
-            line_type: DiffLineType::Addition,
-            old_line_number: None,
-            new_line_number: Some((i + 1) as u32),
-            content: line.to_string(),
+        .map(|(i, raw_line): (usize, &str)| {
+            let (line, has_newline) = match raw_line.strip_suffix('\n') {
+                Some(line) => (line, true),
+                None => (raw_line, false),
+            };
+            let (line, crlf) = match line.strip_suffix('\r') {
+                Some(line) if has_newline => (line, true),
+                _ => (line, false),
+            };
+            DiffLine {
+                line_type: DiffLineType::Addition,
+                old_line_number: None,
+                new_line_number: Some((i + 1) as u32),
+                content: line.to_string(),
+                crlf,
+                no_newline: !has_newline && !truncated,
+            }
         })
         .collect();
 
     let line_count = lines.len() as u32;
 
-    Ok(FileDiff {
-        old_path: None,
-        new_path: path.to_string(),
-        status: FileStatus::Untracked,
-        hunks: vec![DiffHunk {
+    if line_count > 0 {
+        diff.hunks.push(DiffHunk {
             old_start: 0,
             old_lines: 0,
             new_start: 1,
             new_lines: line_count,
             header: format!("@@ -0,0 +1,{} @@ New file", line_count),
             lines,
-        }],
-        is_binary: false,
-        additions: line_count,
-        deletions: 0,
-    })
+            line_offset: 0,
+            truncated: false,
+        });
+    }
+    diff.additions = line_count;
+    finish_diff(&mut diff);
+
+    Ok(diff)
+}
+
+/// Check whether gitattributes mark the file as binary (`binary` or `-diff`)
+fn is_binary_by_attributes(executor: &GitExecutor, path: &str) -> bool {
+    match executor.execute_checked(&["check-attr", "binary", "diff", "--", path]) {
+        Ok(output) => output.lines().any(|line| {
+            line.ends_with(": binary: set") || line.ends_with(": diff: unset")
+        }),
+        Err(_) => false,
+    }
+}
+
+/// Same heuristic as git: a NUL byte near the start means binary content.
+/// UTF-16 text with a byte order mark is decoded instead.
+fn looks_binary(bytes: &[u8]) -> bool {
+    if utf16_bom(bytes).is_some() {
+        return false;
+    }
+    bytes.iter().take(BINARY_SNIFF_BYTES).any(|&b| b == 0)
+}
+
+/// Detect a UTF-16 byte order mark, returning true for little endian
+fn utf16_bom(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [0xFF, 0xFE, ..] => Some(true),
+        [0xFE, 0xFF, ..] => Some(false),
+        _ => None,
+    }
+}
+
+/// Decode file content for display: UTF-8 (with or without BOM), UTF-16 with
+/// a BOM, and Latin-1 as a fallback so any byte sequence can be previewed
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some(little_endian) = utf16_bom(bytes) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| {
+                if little_endian {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                }
+            })
+            .collect();
+        return char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+    }
+
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
 }
 
 pub fn get_file_diff(
@@ -50,6 +167,22 @@ pub fn get_file_diff(
     staged: bool,
     ignore_cr: bool,
 ) -> Result<FileDiff, GitError> {
+    let mut diff = read_file_diff(executor, path, staged, ignore_cr)?;
+    if ignore_cr {
+        // Staging and discarding read the diff with CR changes, their
+        // fingerprint check has to be made against that one
+        diff.fingerprint = read_file_diff(executor, path, staged, false)?.fingerprint;
+    }
+    let new_side = if staged { NewSide::Objects } else { NewSide::Worktree };
+    fill_binary_metadata(executor, &mut diff, new_side);
+    fill_submodule_commits(executor, &mut diff);
+    hide_lfs_pointer_text(&mut diff);
+    Ok(diff)
+}
+
+/// Parse the diff of one file with every hunk git printed, as staging
+/// and discarding need it
+fn read_file_diff(executor: &GitExecutor, path: &str, staged: bool, ignore_cr: bool) -> Result<FileDiff, GitError> {
     let mut args = if staged {
         vec!["diff", "--cached"]
     } else {
@@ -60,6 +193,10 @@ pub fn get_file_diff(
         args.push("--ignore-cr-at-eol");
     }
 
+    // Full blob ids let binary metadata be looked up
+    args.push("--full-index");
+    // Keep "Subproject commit" lines whatever diff.submodule is set to
+    args.push("--submodule=short");
     args.push("--");
     args.push(path);
 
@@ -76,9 +213,52 @@ pub fn get_commit_diff(executor: &GitExecutor, hash: &str) -> Result<Vec<FileDif
         "--format=",
         "--first-parent",
         "--ignore-cr-at-eol",
+        "--full-index",
+        "--submodule=short",
         hash,
     ])?;
-    parse_multi_diff(&output)
+    let mut diffs = parse_multi_diff(&output)?;
+    for diff in &mut diffs {
+        fill_binary_metadata(executor, diff, NewSide::Objects);
+        fill_submodule_commits(executor, diff);
+        hide_lfs_pointer_text(diff);
+    }
+    Ok(diffs)
+}
+
+/// Get the diff of one file in a commit, as listed by `get_file_log`.
+/// `old_path` is the path before a rename so both sides can be shown.
+pub fn get_file_diff_at_commit(
+    executor: &GitExecutor,
+    hash: &str,
+    path: &str,
+    old_path: Option<&str>,
+) -> Result<FileDiff, GitError> {
+    let mut args = vec![
+        "show",
+        "--format=",
+        "--first-parent",
+        "--ignore-cr-at-eol",
+        "--full-index",
+        "--submodule=short",
+        "-M",
+        hash,
+        "--",
+        path,
+    ];
+    if let Some(old) = old_path {
+        args.push(old);
+    }
+
+    let output = executor.execute_checked(&args)?;
+    let mut diff = parse_multi_diff(&output)?
+        .into_iter()
+        .find(|d| d.new_path == path)
+        .unwrap_or_else(|| empty_file_diff(path, FileStatus::Unmodified));
+    fill_binary_metadata(executor, &mut diff, NewSide::Objects);
+    fill_submodule_commits(executor, &mut diff);
+    hide_lfs_pointer_text(&mut diff);
+    Ok(diff)
 }
 
 pub fn get_stash_diff(executor: &GitExecutor, index: u32) -> Result<Vec<FileDiff>, GitError> {
@@ -89,33 +269,76 @@ pub fn get_stash_diff(executor: &GitExecutor, index: u32) -> Result<Vec<FileDiff
         "show",
         "-p",
         "--ignore-cr-at-eol",
+        "--full-index",
+        "--submodule=short",
         &stash_ref,
     ])?;
-    parse_multi_diff(&output)
+    let mut diffs = parse_multi_diff(&output)?;
+    for diff in &mut diffs {
+        fill_binary_metadata(executor, diff, NewSide::Objects);
+        fill_submodule_commits(executor, diff);
+        hide_lfs_pointer_text(diff);
+    }
+    Ok(diffs)
 }
 
-fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
-    let mut diff = FileDiff {
+fn empty_file_diff(path: &str, status: FileStatus) -> FileDiff {
+    FileDiff {
         old_path: None,
-        new_path: default_path.to_string(),
-        status: FileStatus::Modified,
+        new_path: path.to_string(),
+        status,
         hunks: Vec::new(),
         is_binary: false,
         additions: 0,
         deletions: 0,
-    };
+        fingerprint: String::new(),
+        old_size: None,
+        new_size: None,
+        truncated: false,
+        old_oid: None,
+        new_oid: None,
+        mime_type: None,
+        old_dimensions: None,
+        new_dimensions: None,
+        old_lfs_pointer: None,
+        new_lfs_pointer: None,
+        total_hunks: 0,
+        total_lines: 0,
+        submodule: None,
+    }
+}
+
+fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
+    let mut diff = empty_file_diff(default_path, FileStatus::Modified);
 
     let mut current_hunk: Option<DiffHunk> = None;
     let mut old_line = 0u32;
     let mut new_line = 0u32;
+    let mut is_gitlink = false;
+
+    // Split on '\n' only so the carriage return of CRLF lines can be recorded
+    for raw_line in output.split('\n') {
+        let (line, crlf) = match raw_line.strip_suffix('\r') {
+            Some(stripped) => (stripped, true),
+            None => (raw_line, false),
+        };
 
-    for line in output.lines() {
         if line.starts_with("diff --git") {
             // Parse file paths
             if let Some(paths) = parse_diff_header(line) {
                 diff.old_path = Some(paths.0);
                 diff.new_path = paths.1;
             }
+        } else if current_hunk.is_none() && line.ends_with(" 160000") && !line.starts_with("index ") {
+            // "new file mode 160000", "deleted file mode 160000", ...
+            is_gitlink = true;
+        } else if line.starts_with("index ") && current_hunk.is_none() {
+            is_gitlink |= line.ends_with(" 160000");
+            // "index <old>..<new> [<mode>]"
+            if let Some((old_oid, new_oid)) = parse_index_line(line) {
+                diff.old_oid = old_oid;
+                diff.new_oid = new_oid;
+            }
         } else if line.starts_with("Binary files") {
             diff.is_binary = true;
         } else if line.starts_with("@@") {
@@ -133,6 +356,8 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
                     new_lines,
                     header: line.to_string(),
                     lines: Vec::new(),
+                    line_offset: 0,
+                    truncated: false,
                 });
                 old_line = old_start;
                 new_line = new_start;
@@ -146,6 +371,12 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
                 (DiffLineType::Deletion, &line[1..])
             } else if line.starts_with(' ') {
                 (DiffLineType::Context, &line[1..])
+            } else if line.starts_with('\\') {
+                // "\ No newline at end of file" applies to the previous line
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.no_newline = true;
+                }
+                continue;
             } else {
                 continue;
             };
@@ -157,6 +388,8 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
                         old_line_number: None,
                         new_line_number: Some(new_line),
                         content: content.to_string(),
+                        crlf,
+                        no_newline: false,
                     };
                     new_line += 1;
                     l
@@ -167,6 +400,8 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
                         old_line_number: Some(old_line),
                         new_line_number: None,
                         content: content.to_string(),
+                        crlf,
+                        no_newline: false,
                     };
                     old_line += 1;
                     l
@@ -177,6 +412,8 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
                         old_line_number: Some(old_line),
                         new_line_number: Some(new_line),
                         content: content.to_string(),
+                        crlf,
+                        no_newline: false,
                     };
                     old_line += 1;
                     new_line += 1;
@@ -194,26 +431,296 @@ fn parse_diff(output: &str, default_path: &str) -> Result<FileDiff, GitError> {
         diff.hunks.push(hunk);
     }
 
+    if is_gitlink {
+        detect_submodule_change(&mut diff);
+    } else {
+        detect_lfs_pointers(&mut diff);
+    }
+    finish_diff(&mut diff);
+
     Ok(diff)
 }
 
-fn parse_multi_diff(output: &str) -> Result<Vec<FileDiff>, GitError> {
+fn parse_index_line(line: &str) -> Option<(Option<String>, Option<String>)> {
+    let range = line.strip_prefix("index ")?.split_whitespace().next()?;
+    let (old, new) = range.split_once("..")?;
+    let oid = |s: &str| {
+        if s.chars().all(|c| c == '0') {
+            None
+        } else {
+            Some(s.to_string())
+        }
+    };
+    Some((oid(old), oid(new)))
+}
+
+/// Record the Git LFS pointers described by both sides of the diff
+fn detect_lfs_pointers(diff: &mut FileDiff) {
+    let side_text = |excluded: DiffLineType| -> String {
+        diff.hunks
+            .iter()
+            .flat_map(|hunk| hunk.lines.iter())
+            .filter(|line| line.line_type != excluded)
+            .map(|line| format!("{}\n", line.content))
+            .collect()
+    };
+    let old_text = side_text(DiffLineType::Addition);
+    let new_text = side_text(DiffLineType::Deletion);
+
+    diff.old_lfs_pointer = parse_lfs_pointer(&old_text);
+    diff.new_lfs_pointer = parse_lfs_pointer(&new_text);
+
+    if let Some(ref pointer) = diff.old_lfs_pointer {
+        diff.old_size = Some(pointer.size);
+    }
+    if let Some(ref pointer) = diff.new_lfs_pointer {
+        diff.new_size = Some(pointer.size);
+    }
+}
+
+/// Drop the hunks of a diff between two Git LFS pointers (or a pointer and
+/// nothing): the pointer text means nothing to the user. Only for display,
+/// staging still needs the hunks.
+fn hide_lfs_pointer_text(diff: &mut FileDiff) {
+    if diff.old_lfs_pointer.is_none() && diff.new_lfs_pointer.is_none() {
+        return;
+    }
+    let side_is_pointer = |pointer: bool, excluded: DiffLineType| {
+        pointer
+            || diff
+                .hunks
+                .iter()
+                .flat_map(|hunk| hunk.lines.iter())
+                .all(|line| line.line_type == excluded)
+    };
+    let old_is_pointer = side_is_pointer(diff.old_lfs_pointer.is_some(), DiffLineType::Addition);
+    let new_is_pointer = side_is_pointer(diff.new_lfs_pointer.is_some(), DiffLineType::Deletion);
+
+    if old_is_pointer && new_is_pointer {
+        diff.hunks.clear();
+        diff.additions = 0;
+        diff.deletions = 0;
+        diff.total_hunks = 0;
+        diff.total_lines = 0;
+    }
+}
+
+/// Where the new side of a diff lives
+#[derive(Clone, Copy, PartialEq)]
+enum NewSide {
+    /// In the object database (staged and commit diffs)
+    Objects,
+    /// In the working tree (unstaged diffs)
+    Worktree,
+}
+
+/// Fill sizes, MIME type and image dimensions of a binary diff
+fn fill_binary_metadata(executor: &GitExecutor, diff: &mut FileDiff, new_side: NewSide) {
+    if !diff.is_binary {
+        return;
+    }
+
+    let old_bytes = diff.old_oid.as_deref().and_then(|oid| {
+        diff.old_size = blob_size(executor, oid);
+        read_blob_prefix(executor, oid)
+    });
+
+    let new_bytes = if new_side == NewSide::Worktree {
+        let full_path = Path::new(executor.repo_path()).join(&diff.new_path);
+        diff.new_size = fs::metadata(&full_path).ok().map(|m| m.len());
+        let mut bytes = Vec::new();
+        fs::File::open(&full_path)
+            .and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut bytes))
+            .ok()
+            .map(|_| bytes)
+    } else {
+        diff.new_oid.as_deref().and_then(|oid| {
+            diff.new_size = blob_size(executor, oid);
+            read_blob_prefix(executor, oid)
+        })
+    };
+
+    diff.old_dimensions = old_bytes.as_deref().and_then(image_dimensions);
+    diff.new_dimensions = new_bytes.as_deref().and_then(image_dimensions);
+    diff.mime_type = new_bytes
+        .as_deref()
+        .or(old_bytes.as_deref())
+        .and_then(sniff_mime_type)
+        .map(str::to_string);
+}
+
+fn blob_size(executor: &GitExecutor, oid: &str) -> Option<u64> {
+    executor
+        .execute_checked(&["cat-file", "-s", oid])
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+}
+
+fn read_blob_prefix(executor: &GitExecutor, oid: &str) -> Option<Vec<u8>> {
+    executor
+        .execute_raw_limited(&["cat-file", "blob", oid], SNIFF_BYTES)
+        .ok()
+}
+
+/// Record the fingerprint and totals of a fully parsed diff
+fn finish_diff(diff: &mut FileDiff) {
+    diff.fingerprint = compute_fingerprint(diff);
+    diff.total_hunks = diff.hunks.len();
+    diff.total_lines = diff.hunks.iter().map(|h| h.lines.len()).sum();
+}
+
+/// Drop the hunks of each diff past the caps of `limits`. Files past
+/// `max_files` lose all their hunks but keep their paths and counts.
+pub fn apply_limits(diffs: &mut [FileDiff], limits: &DiffLimits) {
+    for (index, diff) in diffs.iter_mut().enumerate() {
+        if limits.max_files.is_some_and(|max| index >= max) {
+            diff.truncated |= !diff.hunks.is_empty();
+            diff.hunks.clear();
+        } else {
+            apply_file_limits(diff, limits);
+        }
+    }
+}
+
+/// Drop the lines of one diff past the line and byte caps of `limits`. A
+/// hunk too large to fit is cut, and marked as truncated.
+pub fn apply_file_limits(diff: &mut FileDiff, limits: &DiffLimits) {
+    let (hunks, next) = take_within_limits(&diff.hunks, DiffCursor::default(), limits, false);
+    if next.is_some() {
+        diff.hunks = hunks;
+        diff.truncated = true;
+    }
+}
+
+/// Lines from `start` on that fit in the line and byte caps, as hunks (the
+/// first and last possibly cut), and the position of the first line left
+/// out. With `at_least_one`, a line is taken even if it alone is over the
+/// caps, so every line can be reached.
+fn take_within_limits(
+    hunks: &[DiffHunk],
+    start: DiffCursor,
+    limits: &DiffLimits,
+    at_least_one: bool,
+) -> (Vec<DiffHunk>, Option<DiffCursor>) {
+    let mut taken = Vec::new();
+    let mut lines = 0;
+    let mut bytes = 0;
+
+    for (index, hunk) in hunks.iter().enumerate().skip(start.hunk) {
+        let first = if index == start.hunk { start.line.min(hunk.lines.len()) } else { 0 };
+        let mut end = first;
+        for line in &hunk.lines[first..] {
+            // Count the newline and the +/- prefix of each line
+            let line_bytes = line.content.len() + 2;
+            let over_lines = limits.max_lines_per_file.is_some_and(|max| lines + 1 > max);
+            let over_bytes = limits.max_bytes_per_file.is_some_and(|max| bytes + line_bytes > max);
+            if (over_lines || over_bytes) && !(at_least_one && lines == 0) {
+                break;
+            }
+            lines += 1;
+            bytes += line_bytes;
+            end += 1;
+        }
+
+        let cut = end < hunk.lines.len();
+        if end > first || !cut {
+            taken.push(DiffHunk {
+                lines: hunk.lines[first..end].to_vec(),
+                line_offset: first,
+                truncated: cut,
+                ..hunk.clone()
+            });
+        }
+        if cut {
+            return (taken, Some(DiffCursor { hunk: index, line: end }));
+        }
+    }
+
+    (taken, None)
+}
+
+/// Load the lines of a file diff from `start` on, within `limits`. At least
+/// one line is returned, however large, so every line can be reached.
+pub fn get_diff_hunks(
+    executor: &GitExecutor,
+    target: &DiffTarget,
+    path: &str,
+    old_path: Option<&str>,
+    start: DiffCursor,
+    ignore_cr: bool,
+    limits: &DiffLimits,
+) -> Result<DiffHunkPage, GitError> {
+    let diff = match target {
+        DiffTarget::Unstaged => get_file_diff(executor, path, false, ignore_cr)?,
+        DiffTarget::Staged => get_file_diff(executor, path, true, ignore_cr)?,
+        DiffTarget::Untracked => get_untracked_file_diff(executor, path)?,
+        DiffTarget::Commit { hash } => get_file_diff_at_commit(executor, hash, path, old_path)?,
+        DiffTarget::Stash { index } => get_stash_diff(executor, *index)?
+            .into_iter()
+            .find(|d| d.new_path == path)
+            .unwrap_or_else(|| empty_file_diff(path, FileStatus::Unmodified)),
+    };
+
+    let (hunks, next) = take_within_limits(&diff.hunks, start, limits, true);
+
+    Ok(DiffHunkPage {
+        start_hunk: start.hunk,
+        start_line: start.line,
+        hunks,
+        total_hunks: diff.total_hunks,
+        has_more: next.is_some(),
+        next,
+        fingerprint: diff.fingerprint,
+    })
+}
+
+/// Hash the paths and hunks of a parsed diff so a later operation can
+/// detect that the file changed since the diff was displayed
+fn compute_fingerprint(diff: &FileDiff) -> String {
+    let mut data = String::new();
+    data.push_str(diff.old_path.as_deref().unwrap_or(""));
+    data.push('\0');
+    data.push_str(&diff.new_path);
+
+    for hunk in &diff.hunks {
+        data.push('\0');
+        data.push_str(&hunk.header);
+        for line in &hunk.lines {
+            data.push('\n');
+            data.push(match line.line_type {
+                DiffLineType::Addition => '+',
+                DiffLineType::Deletion => '-',
+                _ => ' ',
+            });
+            data.push_str(&line.content);
+            if line.crlf {
+                data.push('\r');
+            }
+            if line.no_newline {
+                data.push_str("\n\\");
+            }
+        }
+    }
+
+    format!("{:x}", md5::compute(data))
+}
+
+pub fn parse_multi_diff(output: &str) -> Result<Vec<FileDiff>, GitError> {
     let mut diffs = Vec::new();
     let mut current_diff = String::new();
     let mut current_path = String::new();
 
-    for line in output.lines() {
+    for line in output.split_inclusive('\n') {
         if line.starts_with("diff --git") {
             if !current_diff.is_empty() {
                 diffs.push(parse_diff(&current_diff, &current_path)?);
             }
-            current_diff = line.to_string() + "\n";
+            current_diff = line.to_string();
             if let Some(paths) = parse_diff_header(line) {
                 current_path = paths.1;
             }
         } else {
             current_diff.push_str(line);
-            current_diff.push('\n');
         }
     }
 
@@ -257,143 +764,360 @@ fn parse_range(range: &str) -> (u32, u32) {
     (start, lines)
 }
 
+/// Fail if the diff the UI based its selection on is no longer the current diff
+fn ensure_diff_unchanged(diff: &FileDiff, expected_fingerprint: Option<&str>) -> Result<(), GitError> {
+    match expected_fingerprint {
+        Some(expected) if expected != diff.fingerprint => Err(GitError::DiffChanged {
+            path: diff.new_path.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Select every added and deleted line of the given hunks
+fn select_hunks(diff: &FileDiff, hunk_indices: &[usize]) -> HashMap<usize, Vec<usize>> {
+    let mut selection = HashMap::new();
+
+    for &hunk_idx in hunk_indices {
+        if let Some(hunk) = diff.hunks.get(hunk_idx) {
+            let lines: Vec<usize> = hunk
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| {
+                    matches!(line.line_type, DiffLineType::Addition | DiffLineType::Deletion)
+                })
+                .map(|(i, _)| i)
+                .collect();
+            selection.insert(hunk_idx, lines);
+        }
+    }
+
+    selection
+}
+
+/// Build a partial patch for the selection and feed it to `git apply`
+fn apply_selection(
+    executor: &GitExecutor,
+    path: &str,
+    diff: &FileDiff,
+    line_indices_by_hunk: &HashMap<usize, Vec<usize>>,
+    reverse: bool,
+    cached: bool,
+) -> Result<(), GitError> {
+    let patch = match generate_partial_patch(path, diff, line_indices_by_hunk, reverse)? {
+        Some(patch) => patch,
+        None => return Ok(()),
+    };
+
+    let mut args = vec!["apply"];
+    if cached {
+        args.push("--cached");
+    }
+    if reverse {
+        args.push("--reverse");
+    }
+    if patch.zero_context {
+        args.push("--unidiff-zero");
+    }
+    executor.execute_with_stdin(&args, &patch.text)?;
+
+    Ok(())
+}
+
 /// Stage specific lines from a file's diff
 /// line_indices_by_hunk: HashMap<hunk_index, Vec<line_index>>
 pub fn stage_lines(
     executor: &GitExecutor,
     path: &str,
-    line_indices_by_hunk: std::collections::HashMap<usize, Vec<usize>>,
+    line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<&str>,
 ) -> Result<(), GitError> {
     // Get the current unstaged diff (without ignore_cr for staging)
-    let diff = get_file_diff(executor, path, false, false)?;
-
-    // Generate a partial patch
-    let patch = generate_partial_patch(&diff, &line_indices_by_hunk, false)?;
-
-    if patch.is_empty() {
-        return Ok(());
-    }
-
-    // Apply the patch to the index
-    executor.execute_with_stdin(&["apply", "--cached", "--unidiff-zero"], &patch)?;
+    let diff = read_file_diff(executor, path, false, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
 
-    Ok(())
+    // Apply the partial patch to the index
+    apply_selection(executor, path, &diff, &line_indices_by_hunk, false, true)
 }
 
 /// Unstage specific lines from a file's staged diff
 pub fn unstage_lines(
     executor: &GitExecutor,
     path: &str,
-    line_indices_by_hunk: std::collections::HashMap<usize, Vec<usize>>,
+    line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<&str>,
 ) -> Result<(), GitError> {
     // Get the current staged diff (without ignore_cr for unstaging)
-    let diff = get_file_diff(executor, path, true, false)?;
+    let diff = read_file_diff(executor, path, true, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
 
-    // Generate a partial patch (reversed for unstaging)
-    let patch = generate_partial_patch(&diff, &line_indices_by_hunk, true)?;
+    // Apply the reversed partial patch to the index
+    apply_selection(executor, path, &diff, &line_indices_by_hunk, true, true)
+}
 
-    if patch.is_empty() {
-        return Ok(());
-    }
+/// Discard specific lines of a file's unstaged diff from the working tree
+pub fn discard_lines(
+    executor: &GitExecutor,
+    path: &str,
+    line_indices_by_hunk: HashMap<usize, Vec<usize>>,
+    expected_fingerprint: Option<&str>,
+) -> Result<(), GitError> {
+    let diff = read_file_diff(executor, path, false, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
 
-    // Apply the reversed patch to the index
-    executor.execute_with_stdin(&["apply", "--cached", "--unidiff-zero", "--reverse"], &patch)?;
+    // Reverse-apply the selected changes to the working tree only
+    apply_selection(executor, path, &diff, &line_indices_by_hunk, true, false)
+}
 
-    Ok(())
+/// Stage whole hunks from a file's unstaged diff
+pub fn stage_hunks(
+    executor: &GitExecutor,
+    path: &str,
+    hunk_indices: &[usize],
+    expected_fingerprint: Option<&str>,
+) -> Result<(), GitError> {
+    let diff = read_file_diff(executor, path, false, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
+
+    let selection = select_hunks(&diff, hunk_indices);
+    apply_selection(executor, path, &diff, &selection, false, true)
 }
 
-/// Generate a patch containing only the selected lines
-fn generate_partial_patch(
-    diff: &FileDiff,
-    line_indices_by_hunk: &std::collections::HashMap<usize, Vec<usize>>,
-    _reverse: bool,
-) -> Result<String, GitError> {
-    let mut patch = String::new();
-
-    // Add diff header
-    let old_path = diff.old_path.as_ref().unwrap_or(&diff.new_path);
-    patch.push_str(&format!("diff --git a/{} b/{}\n", old_path, diff.new_path));
-    patch.push_str(&format!("--- a/{}\n", old_path));
-    patch.push_str(&format!("+++ b/{}\n", diff.new_path));
-
-    for (hunk_idx, hunk) in diff.hunks.iter().enumerate() {
-        let selected_indices = match line_indices_by_hunk.get(&hunk_idx) {
-            Some(indices) => indices,
-            None => continue,
+/// Unstage whole hunks from a file's staged diff
+pub fn unstage_hunks(
+    executor: &GitExecutor,
+    path: &str,
+    hunk_indices: &[usize],
+    expected_fingerprint: Option<&str>,
+) -> Result<(), GitError> {
+    let diff = read_file_diff(executor, path, true, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
+
+    let selection = select_hunks(&diff, hunk_indices);
+    apply_selection(executor, path, &diff, &selection, true, true)
+}
+
+/// Discard whole hunks of a file's unstaged diff from the working tree
+pub fn discard_hunks(
+    executor: &GitExecutor,
+    path: &str,
+    hunk_indices: &[usize],
+    expected_fingerprint: Option<&str>,
+) -> Result<(), GitError> {
+    let diff = read_file_diff(executor, path, false, false)?;
+    ensure_diff_unchanged(&diff, expected_fingerprint)?;
+
+    let selection = select_hunks(&diff, hunk_indices);
+    apply_selection(executor, path, &diff, &selection, true, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::{init_repo, png};
+
+    fn commit_all(executor: &GitExecutor, message: &str) -> String {
+        executor.execute_checked(&["add", "-A"]).unwrap();
+        executor.execute_checked(&["commit", "-q", "-m", message]).unwrap();
+        executor.execute_checked(&["rev-parse", "HEAD"]).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn binary_commit_diff_has_blob_metadata() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join("logo.png"), png(16, 16)).unwrap();
+        commit_all(&executor, "add logo");
+        fs::write(dir.path().join("logo.png"), png(32, 24)).unwrap();
+        let hash = commit_all(&executor, "resize logo");
+
+        let diffs = get_commit_diff(&executor, &hash).unwrap();
+        let diff = &diffs[0];
+        assert!(diff.is_binary);
+        assert_eq!(diff.old_oid.as_ref().map(|o| o.len()), Some(40));
+        assert_eq!(diff.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(diff.old_dimensions, Some(ImageDimensions { width: 16, height: 16 }));
+        assert_eq!(diff.new_dimensions, Some(ImageDimensions { width: 32, height: 24 }));
+        assert_eq!(diff.new_size, Some(png(32, 24).len() as u64));
+    }
+
+    #[test]
+    fn lfs_pointer_diff_shows_pointer() {
+        let (dir, executor) = init_repo();
+        let pointer = |oid: &str, size: u64| {
+            format!("version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n", oid, size)
         };
+        fs::write(dir.path().join("video.mp4"), pointer(&"a".repeat(64), 100)).unwrap();
+        commit_all(&executor, "add video");
+        fs::write(dir.path().join("video.mp4"), pointer(&"b".repeat(64), 2048)).unwrap();
 
-        if selected_indices.is_empty() {
-            continue;
-        }
+        let diff = get_file_diff(&executor, "video.mp4", false, false).unwrap();
+        assert!(diff.hunks.is_empty());
+        assert_eq!(diff.old_lfs_pointer.as_ref().map(|p| p.size), Some(100));
+        assert_eq!(diff.new_lfs_pointer.as_ref().map(|p| p.oid.clone()), Some("b".repeat(64)));
+        assert_eq!(diff.new_size, Some(2048));
 
-        // Build partial hunk with selected lines
-        let mut hunk_lines = String::new();
-        let mut old_count = 0u32;
-        let mut new_count = 0u32;
-        let mut old_start = hunk.old_start;
-        let mut new_start = hunk.new_start;
-        let mut first_line_found = false;
+        // Staging still sees the pointer text
+        stage_hunks(&executor, "video.mp4", &[0], Some(&diff.fingerprint)).unwrap();
+        let staged = executor.execute_checked(&["diff", "--cached", "--name-only"]).unwrap();
+        assert_eq!(staged, "video.mp4\n");
+    }
 
-        for (line_idx, line) in hunk.lines.iter().enumerate() {
-            let is_selected = selected_indices.contains(&line_idx);
+    #[test]
+    fn crlf_only_change_can_be_staged_from_the_displayed_diff() {
+        let (dir, executor) = init_repo();
+        executor.execute_checked(&["config", "core.autocrlf", "false"]).unwrap();
+        fs::write(dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        commit_all(&executor, "base");
+        fs::write(dir.path().join("a.txt"), "one\r\ntwo\r\n").unwrap();
 
-            match line.line_type {
-                DiffLineType::Context => {
-                    // Always include context lines
-                    hunk_lines.push(' ');
-                    hunk_lines.push_str(&line.content);
-                    hunk_lines.push('\n');
-                    old_count += 1;
-                    new_count += 1;
-                    if !first_line_found {
-                        first_line_found = true;
-                    }
-                }
-                DiffLineType::Addition => {
-                    if is_selected {
-                        hunk_lines.push('+');
-                        hunk_lines.push_str(&line.content);
-                        hunk_lines.push('\n');
-                        new_count += 1;
-                        if !first_line_found {
-                            first_line_found = true;
-                        }
-                    }
-                }
-                DiffLineType::Deletion => {
-                    if is_selected {
-                        hunk_lines.push('-');
-                        hunk_lines.push_str(&line.content);
-                        hunk_lines.push('\n');
-                        old_count += 1;
-                        if !first_line_found {
-                            first_line_found = true;
-                        }
-                    }
-                }
-                _ => {}
-            }
+        let diff = get_file_diff(&executor, "a.txt", false, true).unwrap();
+        assert!(diff.hunks.is_empty());
+        assert_eq!(diff.fingerprint, get_file_diff(&executor, "a.txt", false, false).unwrap().fingerprint);
 
-            // Track the start position
-            if !first_line_found {
-                if line.old_line_number.is_some() {
-                    old_start = line.old_line_number.unwrap() + 1;
-                }
-                if line.new_line_number.is_some() {
-                    new_start = line.new_line_number.unwrap() + 1;
-                }
+        stage_hunks(&executor, "a.txt", &[0], Some(&diff.fingerprint)).unwrap();
+        let staged = executor.execute_checked(&["diff", "--cached", "--name-only"]).unwrap();
+        assert_eq!(staged, "a.txt\n");
+    }
+
+    #[test]
+    fn limited_diff_loads_remaining_hunks_on_demand() {
+        let (dir, executor) = init_repo();
+        let numbered = |changed: &[usize]| -> String {
+            (1..=60)
+                .map(|i| if changed.contains(&i) { format!("line {} changed\n", i) } else { format!("line {}\n", i) })
+                .collect()
+        };
+        fs::write(dir.path().join("a.txt"), numbered(&[])).unwrap();
+        commit_all(&executor, "base");
+        fs::write(dir.path().join("a.txt"), numbered(&[5, 30, 55])).unwrap();
+        let limits = DiffLimits {
+            max_lines_per_file: Some(10),
+            ..Default::default()
+        };
+
+        let mut diff = get_file_diff(&executor, "a.txt", false, false).unwrap();
+        apply_file_limits(&mut diff, &limits);
+        assert!(diff.truncated);
+        // The second hunk is cut after the first 10 lines
+        assert_eq!((diff.hunks.len(), diff.total_hunks), (2, 3));
+        assert_eq!(diff.total_lines, 24);
+        assert_eq!((diff.hunks[1].lines.len(), diff.hunks[1].truncated), (2, true));
+
+        let start = DiffCursor { hunk: 1, line: 0 };
+        let page = get_diff_hunks(&executor, &DiffTarget::Unstaged, "a.txt", None, start, false, &limits).unwrap();
+        assert_eq!((page.start_hunk, page.hunks.len()), (1, 2));
+        assert!(!page.hunks[0].truncated);
+        assert_eq!(page.next, Some(DiffCursor { hunk: 2, line: 2 }));
+        assert_eq!(page.fingerprint, diff.fingerprint);
+        assert_eq!(page.hunks[0].new_start, 27);
+
+        // A page always holds at least one line
+        let tiny = DiffLimits {
+            max_bytes_per_file: Some(1),
+            ..Default::default()
+        };
+        let start = DiffCursor { hunk: 2, line: 7 };
+        let page = get_diff_hunks(&executor, &DiffTarget::Unstaged, "a.txt", None, start, false, &tiny).unwrap();
+        assert_eq!(page.hunks[0].lines.len(), 1);
+        assert_eq!(page.hunks[0].line_offset, 7);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn huge_hunk_is_paged_by_lines() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join("lock.txt"), "old\n").unwrap();
+        commit_all(&executor, "base");
+        let content: String = (0..12_000).map(|i| format!("dependency {}\n", i)).collect();
+        fs::write(dir.path().join("lock.txt"), content).unwrap();
+        let limits = DiffLimits {
+            max_lines_per_file: Some(5_000),
+            ..Default::default()
+        };
+
+        let mut diff = get_file_diff(&executor, "lock.txt", false, false).unwrap();
+        assert_eq!((diff.total_hunks, diff.total_lines), (1, 12_001));
+        apply_file_limits(&mut diff, &limits);
+        assert!(diff.truncated);
+        assert_eq!(diff.hunks[0].lines.len(), 5_000);
+        assert!(diff.hunks[0].truncated);
+
+        let mut start = DiffCursor { hunk: 0, line: 5_000 };
+        let mut loaded = 5_000;
+        loop {
+            let page = get_diff_hunks(&executor, &DiffTarget::Unstaged, "lock.txt", None, start, false, &limits).unwrap();
+            let hunk = &page.hunks[0];
+            assert_eq!(hunk.line_offset, loaded);
+            assert!(hunk.lines.len() <= 5_000);
+            loaded += hunk.lines.len();
+            assert_eq!(hunk.truncated, page.has_more);
+            match page.next {
+                Some(next) => start = next,
+                None => break,
             }
         }
+        assert_eq!(loaded, 12_001);
+    }
 
-        if old_count > 0 || new_count > 0 {
-            // Add hunk header
-            patch.push_str(&format!(
-                "@@ -{},{} +{},{} @@\n",
-                old_start, old_count, new_start, new_count
-            ));
-            patch.push_str(&hunk_lines);
-        }
+    #[test]
+    fn untracked_binary_file_has_no_hunks() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join("image.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        let diff = get_untracked_file_diff(&executor, "image.png").unwrap();
+        assert!(diff.is_binary);
+        assert!(diff.hunks.is_empty());
+        assert_eq!(diff.new_size, Some(16));
     }
 
-    Ok(patch)
+    #[test]
+    fn untracked_file_marked_binary_by_attributes() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join(".gitattributes"), "*.dat binary\n").unwrap();
+        fs::write(dir.path().join("data.dat"), "plain text\n").unwrap();
+
+        let diff = get_untracked_file_diff(&executor, "data.dat").unwrap();
+        assert!(diff.is_binary);
+    }
+
+    #[test]
+    fn large_untracked_file_is_truncated_on_a_line_boundary() {
+        let (dir, executor) = init_repo();
+        let line = "0123456789abcdef\n";
+        let content = line.repeat((MAX_UNTRACKED_PREVIEW_BYTES as usize / line.len()) + 10);
+        fs::write(dir.path().join("big.txt"), &content).unwrap();
+
+        let diff = get_untracked_file_diff(&executor, "big.txt").unwrap();
+        assert!(diff.truncated);
+        assert_eq!(diff.new_size, Some(content.len() as u64));
+        let lines = &diff.hunks[0].lines;
+        assert_eq!(lines.len(), MAX_UNTRACKED_PREVIEW_BYTES as usize / line.len());
+        assert!(lines.iter().all(|l| l.content == "0123456789abcdef" && !l.no_newline));
+    }
+
+    #[test]
+    fn untracked_non_utf8_file_is_decoded() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join("latin1.txt"), b"caf\xe9\r\nna\xefve").unwrap();
+
+        let diff = get_untracked_file_diff(&executor, "latin1.txt").unwrap();
+        let lines = &diff.hunks[0].lines;
+        assert_eq!(lines[0].content, "café");
+        assert!(lines[0].crlf);
+        assert_eq!(lines[1].content, "naïve");
+        assert!(lines[1].no_newline);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn untracked_symlink_shows_target() {
+        let (dir, executor) = init_repo();
+        std::os::unix::fs::symlink("target/file.txt", dir.path().join("link")).unwrap();
+
+        let diff = get_untracked_file_diff(&executor, "link").unwrap();
+        assert!(!diff.is_binary);
+        assert_eq!(diff.hunks[0].lines[0].content, "target/file.txt");
+        assert!(diff.hunks[0].lines[0].no_newline);
+    }
 }
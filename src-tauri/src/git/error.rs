@@ -33,6 +33,18 @@ pub enum GitError {
     #[error("Remote {name} not found")]
     RemoteNotFound { name: String },
 
+    #[error("Revision {revision} not found")]
+    RevisionNotFound { revision: String },
+
+    #[error("Diff for {path} changed since it was displayed, refresh and try again")]
+    DiffChanged { path: String },
+
+    #[error("Hook {hook} failed with exit code {exit_code}:\n{output}")]
+    HookFailed { hook: String, exit_code: i32, output: String },
+
+    #[error("Signing failed: {message}")]
+    SigningFailed { message: String },
+
     #[error("IO error: {message}")]
     IoError { message: String },
 }
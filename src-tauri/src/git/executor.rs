@@ -1,6 +1,8 @@
 use std::collections::HashMap;
-use std::process::{Command, Stdio};
-use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+use std::io::{BufRead, BufReader, Read, Write};
+use serde::{Deserialize, Serialize};
 use crate::git::error::GitError;
 use crate::git::path::find_git_executable;
 
@@ -27,6 +29,14 @@ pub fn build_ssh_command(key_path: &str) -> String {
     }
 }
 
+/// Output pipe a line was read from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
 #[derive(Debug)]
 pub struct CommandResult {
     pub stdout: String,
@@ -98,7 +108,179 @@ impl GitExecutor {
         Ok(output.stdout)
     }
 
+    /// Execute a git command and return at most `limit` bytes of its output,
+    /// stopping the process once enough has been read
+    pub fn execute_raw_limited(&self, args: &[&str], limit: u64) -> Result<Vec<u8>, GitError> {
+        let mut child = Command::new(&self.git_path)
+            .current_dir(&self.repo_path)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| GitError::IoError {
+                message: e.to_string(),
+            })?;
+
+        let stderr = drain_stderr(&mut child);
+        let mut bytes = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            stdout.take(limit).read_to_end(&mut bytes).map_err(|e| GitError::IoError {
+                message: e.to_string(),
+            })?;
+        }
+
+        if bytes.len() as u64 >= limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = stderr.join();
+            return Ok(bytes);
+        }
+
+        let status = child.wait().map_err(|e| GitError::IoError {
+            message: e.to_string(),
+        })?;
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(GitError::CommandFailed {
+                code: status.code().unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&stderr).to_string(),
+            });
+        }
+
+        Ok(bytes)
+    }
+
+    /// Execute a git command and hand each line of its output to `on_line`
+    /// as soon as it is produced
+    pub fn execute_streaming<F>(&self, args: &[&str], mut on_line: F) -> Result<(), GitError>
+    where
+        F: FnMut(&str),
+    {
+        let mut child = Command::new(&self.git_path)
+            .current_dir(&self.repo_path)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| GitError::IoError {
+                message: e.to_string(),
+            })?;
+
+        let stderr = drain_stderr(&mut child);
+        if let Some(stdout) = child.stdout.take() {
+            let mut reader = BufReader::new(stdout);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                let read = reader.read_until(b'\n', &mut buf).map_err(|e| GitError::IoError {
+                    message: e.to_string(),
+                })?;
+                if read == 0 {
+                    break;
+                }
+                let line = String::from_utf8_lossy(&buf);
+                on_line(line.trim_end_matches(['\n', '\r']));
+            }
+        }
+
+        let status = child.wait().map_err(|e| GitError::IoError {
+            message: e.to_string(),
+        })?;
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(GitError::CommandFailed {
+                code: status.code().unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&stderr).to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Execute a git command with extra environment variables, handing each
+    /// line of stdout and stderr to `on_line` as soon as it is produced.
+    /// The full output is returned as well.
+    pub fn execute_with_output<F>(
+        &self,
+        args: &[&str],
+        env_vars: &HashMap<String, String>,
+        mut on_line: F,
+    ) -> Result<CommandResult, GitError>
+    where
+        F: FnMut(OutputStream, &str),
+    {
+        let mut child = Command::new(&self.git_path)
+            .current_dir(&self.repo_path)
+            .args(args)
+            .envs(env_vars)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| GitError::IoError {
+                message: e.to_string(),
+            })?;
+
+        // Both pipes are drained on their own threads so neither can fill up
+        // and block the process, lines are passed back in arrival order
+        let (sender, receiver) = std::sync::mpsc::channel::<(OutputStream, String)>();
+        let mut readers = Vec::new();
+        let pipes: [(OutputStream, Option<Box<dyn Read + Send>>); 2] = [
+            (OutputStream::Stdout, child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>)),
+            (OutputStream::Stderr, child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>)),
+        ];
+        for (stream, pipe) in pipes {
+            let Some(pipe) = pipe else { continue };
+            let sender = sender.clone();
+            readers.push(std::thread::spawn(move || {
+                let mut reader = BufReader::new(pipe);
+                let mut buf = Vec::new();
+                while let Ok(read) = reader.read_until(b'\n', &mut buf) {
+                    if read == 0 {
+                        break;
+                    }
+                    let line = String::from_utf8_lossy(&buf).trim_end_matches(['\n', '\r']).to_string();
+                    if sender.send((stream, line)).is_err() {
+                        break;
+                    }
+                    buf.clear();
+                }
+            }));
+        }
+        drop(sender);
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        for (stream, line) in receiver {
+            on_line(stream, &line);
+            let output = if stream == OutputStream::Stdout { &mut stdout } else { &mut stderr };
+            output.push_str(&line);
+            output.push('\n');
+        }
+        for reader in readers {
+            let _ = reader.join();
+        }
+
+        let status = child.wait().map_err(|e| GitError::IoError {
+            message: e.to_string(),
+        })?;
+
+        Ok(CommandResult {
+            stdout,
+            stderr,
+            exit_code: status.code().unwrap_or(-1),
+        })
+    }
+
     pub fn execute_with_stdin(&self, args: &[&str], stdin_data: &str) -> Result<String, GitError> {
+        let bytes = self.execute_raw_with_stdin(args, stdin_data.as_bytes())?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    /// Execute a git command with data on stdin and return raw bytes
+    pub fn execute_raw_with_stdin(&self, args: &[&str], stdin_data: &[u8]) -> Result<Vec<u8>, GitError> {
         let mut child = Command::new(&self.git_path)
             .current_dir(&self.repo_path)
             .args(args)
@@ -111,7 +293,7 @@ impl GitExecutor {
             })?;
 
         if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(stdin_data.as_bytes()).map_err(|e| GitError::IoError {
+            stdin.write_all(stdin_data).map_err(|e| GitError::IoError {
                 message: e.to_string(),
             })?;
         }
@@ -127,7 +309,7 @@ impl GitExecutor {
             });
         }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(output.stdout)
     }
 
     /// Execute a git command with custom environment variables
@@ -174,3 +356,38 @@ impl GitExecutor {
         Ok(result.stdout)
     }
 }
+
+/// Read the stderr of a child on its own thread, so a command writing many
+/// warnings cannot block on a full pipe while its stdout is being read
+fn drain_stderr(child: &mut Child) -> JoinHandle<Vec<u8>> {
+    let stderr = child.stderr.take();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn streaming_survives_a_flood_of_stderr() {
+        let dir = TempDir::new().unwrap();
+        let executor = GitExecutor::new(dir.path().to_str().unwrap()).unwrap();
+        // Far more than a pipe buffer holds, written before any stdout
+        let flood = "alias.flood=!head -c 1000000 /dev/zero | tr '\\0' w >&2; echo done";
+
+        let mut lines = Vec::new();
+        executor
+            .execute_streaming(&["-c", flood, "flood"], |line| lines.push(line.to_string()))
+            .unwrap();
+        assert_eq!(lines, vec!["done"]);
+        let bytes = executor.execute_raw_limited(&["-c", flood, "flood"], 1024).unwrap();
+        assert_eq!(bytes, b"done\n");
+    }
+}
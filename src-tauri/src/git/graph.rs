@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::git::commit_cache::with_commit_cache;
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::log::{get_commit_log, get_commit_parents, LogQuery};
+use crate::git::types::Commit;
+
+/// Lane assignments carried from one page of the log to the next.
+/// The frontend hands back the state returned with the previous page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphState {
+    /// Commit each lane is waiting for, None for a free lane
+    pub lanes: Vec<Option<String>>,
+    /// Color index of each lane
+    pub colors: Vec<usize>,
+    pub next_color: usize,
+    /// Number of commits laid out so far
+    pub rows: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// Crosses the whole row in its lane without touching the commit
+    Through,
+    /// Comes from the top of the row in its lane into the commit
+    ToNode,
+    /// Leaves the commit towards the bottom of the row in its lane
+    FromNode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphEdge {
+    pub kind: EdgeKind,
+    pub lane: usize,
+    pub color: usize,
+}
+
+/// Layout of one commit row of the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphRow {
+    pub hash: String,
+    /// Lane (column) of the commit
+    pub lane: usize,
+    pub color: usize,
+    pub edges: Vec<GraphEdge>,
+    /// Number of lanes used by the row
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphPage {
+    pub commits: Vec<Commit>,
+    pub rows: Vec<GraphRow>,
+    /// State to pass along when loading the next page
+    pub state: GraphState,
+}
+
+/// Load a page of the log with its graph layout.
+/// Rows before `skip` missing from `state` are laid out first from commit
+/// parents only, so any page can be requested without the previous ones.
+/// Logs filtered by anything but refs and paths leave out commits whose
+/// parents cannot be rewritten, so they have no graph.
+pub fn get_commit_graph(
+    executor: &GitExecutor,
+    query: &LogQuery,
+    count: u32,
+    skip: u32,
+    state: Option<GraphState>,
+) -> Result<GraphPage, GitError> {
+    if !query.keeps_ancestry() {
+        return Err(GitError::ParseError {
+            message: "The commit graph cannot be drawn for a log filtered by author, committer, date, message, content or merges".to_string(),
+        });
+    }
+
+    let mut state = usable_state(state, skip);
+    if state.rows < skip {
+        let earlier = get_commit_parents(executor, query, skip - state.rows, state.rows)?;
+        layout_rows(&mut state, &earlier, query.first_parent);
+    }
+
+    let commits = get_commit_log(executor, query, count, skip, false)?;
+    let parents: Vec<(String, Vec<String>)> = if query.paths.is_empty() {
+        commits
+            .iter()
+            .map(|c| (c.hash.clone(), c.parent_hashes.clone()))
+            .collect()
+    } else {
+        get_commit_parents(executor, query, count, skip)?
+    };
+    let rows = layout_rows(&mut state, &parents, query.first_parent);
+
+    Ok(GraphPage { commits, rows, state })
+}
+
+/// Same as `get_commit_graph` for the unfiltered history, read from the
+/// commit cache
+pub fn get_cached_commit_graph(
+    executor: &GitExecutor,
+    cache_dir: &Path,
+    count: u32,
+    skip: u32,
+    state: Option<GraphState>,
+) -> Result<GraphPage, GitError> {
+    with_commit_cache(executor, cache_dir, |cache| {
+        let mut state = usable_state(state, skip);
+        if state.rows < skip {
+            let earlier = cache.parents(skip - state.rows, state.rows);
+            layout_rows(&mut state, &earlier, false);
+        }
+
+        let rows = layout_rows(&mut state, &cache.parents(count, skip), false);
+        GraphPage {
+            commits: cache.page(count, skip),
+            rows,
+            state,
+        }
+    })
+}
+
+/// The state handed back by the frontend if it can continue the layout at
+/// `skip`, a fresh one to rebuild the earlier rows from otherwise
+fn usable_state(state: Option<GraphState>, skip: u32) -> GraphState {
+    state
+        .filter(|s| s.rows <= skip && s.lanes.len() == s.colors.len() && s.next_color < usize::MAX)
+        .unwrap_or_default()
+}
+
+/// Lay out commits given in topological order, continuing from `state`
+pub fn layout_rows(
+    state: &mut GraphState,
+    commits: &[(String, Vec<String>)],
+    first_parent: bool,
+) -> Vec<GraphRow> {
+    commits
+        .iter()
+        .map(|(hash, parents)| {
+            let parents = if first_parent && parents.len() > 1 { &parents[..1] } else { &parents[..] };
+            layout_row(state, hash, parents)
+        })
+        .collect()
+}
+
+fn layout_row(state: &mut GraphState, hash: &str, parents: &[String]) -> GraphRow {
+    let lanes_before = state.lanes.len();
+    let mut edges = Vec::new();
+
+    // Every lane waiting for this commit ends here; several lanes mean
+    // several children, i.e. a fork point
+    let waiting: Vec<usize> = (0..lanes_before)
+        .filter(|&i| state.lanes[i].as_deref() == Some(hash))
+        .collect();
+
+    let (lane, color) = match waiting.first() {
+        Some(&lane) => (lane, state.colors[lane]),
+        // No child seen yet: a branch tip
+        None => {
+            let lane = state.free_lane();
+            (lane, state.new_color())
+        }
+    };
+
+    for i in 0..lanes_before {
+        let kind = match state.lanes[i] {
+            Some(ref waiting_for) if waiting_for == hash => EdgeKind::ToNode,
+            Some(_) => EdgeKind::Through,
+            None => continue,
+        };
+        edges.push(GraphEdge {
+            kind,
+            lane: i,
+            color: state.colors[i],
+        });
+    }
+    for &i in &waiting {
+        state.lanes[i] = None;
+    }
+
+    state.set_lane(lane, parents.first().cloned(), color);
+    if !parents.is_empty() {
+        edges.push(GraphEdge {
+            kind: EdgeKind::FromNode,
+            lane,
+            color,
+        });
+    }
+
+    // Merged parents join a lane already waiting for them, or open a new one
+    for parent in parents.iter().skip(1) {
+        let existing = state.lanes.iter().position(|l| l.as_deref() == Some(parent.as_str()));
+        let (merge_lane, merge_color) = match existing {
+            Some(i) => (i, state.colors[i]),
+            None => {
+                let i = state.free_lane();
+                let color = state.new_color();
+                state.set_lane(i, Some(parent.clone()), color);
+                (i, color)
+            }
+        };
+        edges.push(GraphEdge {
+            kind: EdgeKind::FromNode,
+            lane: merge_lane,
+            color: merge_color,
+        });
+    }
+
+    let width = lanes_before.max(state.lanes.len()).max(lane + 1);
+    while state.lanes.last().is_some_and(|l| l.is_none()) {
+        state.lanes.pop();
+        state.colors.pop();
+    }
+    state.rows += 1;
+
+    GraphRow {
+        hash: hash.to_string(),
+        lane,
+        color,
+        edges,
+        width,
+    }
+}
+
+impl GraphState {
+    fn free_lane(&mut self) -> usize {
+        match self.lanes.iter().position(|l| l.is_none()) {
+            Some(i) => i,
+            None => {
+                self.lanes.push(None);
+                self.colors.push(0);
+                self.lanes.len() - 1
+            }
+        }
+    }
+
+    fn new_color(&mut self) -> usize {
+        self.next_color += 1;
+        self.next_color - 1
+    }
+
+    fn set_lane(&mut self, lane: usize, waiting_for: Option<String>, color: usize) {
+        self.lanes[lane] = waiting_for;
+        self.colors[lane] = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+
+    fn commit(hash: &str, parents: &[&str]) -> (String, Vec<String>) {
+        (hash.to_string(), parents.iter().map(|p| p.to_string()).collect())
+    }
+
+    // M merges B into A2; A2 and B both fork from A1
+    //
+    //   M
+    //   |\
+    //   A2 B
+    //   |/
+    //   A1
+    fn history() -> Vec<(String, Vec<String>)> {
+        vec![
+            commit("M", &["A2", "B"]),
+            commit("A2", &["A1"]),
+            commit("B", &["A1"]),
+            commit("A1", &[]),
+        ]
+    }
+
+    #[test]
+    fn lays_out_merge_and_fork() {
+        let mut state = GraphState::default();
+        let rows = layout_rows(&mut state, &history(), false);
+
+        let lanes: Vec<usize> = rows.iter().map(|r| r.lane).collect();
+        assert_eq!(lanes, vec![0, 0, 1, 0]);
+        assert_eq!(rows[0].edges.len(), 2);
+        assert_eq!(rows[0].edges[1], GraphEdge { kind: EdgeKind::FromNode, lane: 1, color: 1 });
+        assert_eq!(rows[1].edges[1], GraphEdge { kind: EdgeKind::Through, lane: 1, color: 1 });
+        // Both children end in A1
+        let to_node = rows[3].edges.iter().filter(|e| e.kind == EdgeKind::ToNode).count();
+        assert_eq!(to_node, 2);
+        assert_eq!(rows[2].color, 1);
+        assert!(state.lanes.is_empty());
+    }
+
+    #[test]
+    fn paged_layout_matches_single_pass() {
+        let mut whole = GraphState::default();
+        let expected = layout_rows(&mut whole, &history(), false);
+
+        let mut state = GraphState::default();
+        let mut rows = layout_rows(&mut state, &history()[..2], false);
+        // The merge edge towards B stays open across the page boundary
+        assert_eq!(state.lanes, vec![Some("A1".to_string()), Some("B".to_string())]);
+        rows.extend(layout_rows(&mut state, &history()[2..], false));
+
+        let summary = |rows: &[GraphRow]| -> Vec<(usize, usize, Vec<GraphEdge>)> {
+            rows.iter().map(|r| (r.lane, r.color, r.edges.clone())).collect()
+        };
+        assert_eq!(summary(&rows), summary(&expected));
+        assert_eq!(state.rows, 4);
+    }
+
+    #[test]
+    fn first_parent_ignores_merged_branches() {
+        let mut state = GraphState::default();
+        let history = vec![commit("M", &["A2", "B"]), commit("A2", &["A1"]), commit("A1", &[])];
+        let rows = layout_rows(&mut state, &history, true);
+        assert!(rows.iter().all(|r| r.lane == 0 && r.width == 1));
+    }
+
+    #[test]
+    fn path_filtered_graph_closes_its_lanes_and_rebuilds_bad_state() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        for (file, message) in [("a.txt", "a1"), ("b.txt", "b1"), ("a.txt", "a2"), ("b.txt", "b2")] {
+            let content = std::fs::read_to_string(dir.path().join(file)).unwrap_or_default();
+            std::fs::write(dir.path().join(file), content + message + "\n").unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", message]);
+        }
+
+        let query = LogQuery {
+            paths: vec!["a.txt".into()],
+            ..Default::default()
+        };
+        let page = get_commit_graph(&executor, &query, 10, 0, None).unwrap();
+        // a2's parent is rewritten from b1 to a1, so nothing is left dangling
+        assert_eq!(page.rows.len(), 2);
+        assert!(page.rows.iter().all(|r| r.lane == 0));
+        assert!(page.state.lanes.is_empty());
+
+        let bad = GraphState {
+            lanes: vec![Some("x".into()), None],
+            colors: vec![],
+            next_color: 0,
+            rows: 1,
+        };
+        let page = get_commit_graph(&executor, &query, 10, 1, Some(bad)).unwrap();
+        assert_eq!(page.rows.len(), 1);
+        assert!(page.state.lanes.is_empty());
+
+        let by_author = LogQuery {
+            author: Some("Grist".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_commit_graph(&executor, &by_author, 10, 0, None),
+            Err(GitError::ParseError { .. })
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::{CommandResult, GitExecutor, OutputStream};
+
+/// Hooks git runs on its own, from githooks(5)
+const KNOWN_HOOKS: [&str; 20] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "pre-receive",
+    "update",
+    "post-receive",
+    "post-update",
+    "reference-transaction",
+    "push-to-checkout",
+    "pre-auto-gc",
+    "post-rewrite",
+];
+
+/// Suffix given to a hook file to disable it
+const DISABLED_SUFFIX: &str = ".disabled";
+
+/// A line printed while a command that runs hooks is executing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookOutput {
+    /// Hook that printed the line, None for output of git itself
+    pub hook: Option<String>,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+/// A hook installed in the repository's hooks directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookInfo {
+    pub name: String,
+    pub path: String,
+    /// False when disabled by Grist or, on Unix, not executable
+    pub enabled: bool,
+}
+
+/// Run a git command that may run hooks, handing its output to `on_output`
+/// line by line. When the command fails because a hook did, the error is
+/// `HookFailed` with everything the hook printed; other failures are left to
+/// the caller through the returned exit code.
+///
+/// Hooks are told apart with trace2 events, which git writes to the same
+/// stderr the hooks inherit, so they arrive in order with the hook output.
+/// An event written after hook output without a trailing newline ends up on
+/// the same line, and is split off it.
+pub fn run_hooked<F>(
+    executor: &GitExecutor,
+    args: &[&str],
+    env: &HashMap<String, String>,
+    mut on_output: F,
+) -> Result<CommandResult, GitError>
+where
+    F: FnMut(HookOutput),
+{
+    let mut env = env.clone();
+    env.insert("GIT_TRACE2_EVENT".to_string(), "2".to_string());
+
+    // (hook name, trace2 child id) of the hook running
+    let mut running: Option<(String, u64)> = None;
+    // (hook name, exit code, output) of each hook that ran
+    let mut runs: Vec<(String, i32, String)> = Vec::new();
+    let mut stderr = String::new();
+
+    let result = executor.execute_with_output(args, &env, |stream, line| {
+        let (line, event) = match stream {
+            OutputStream::Stderr => split_trace_event(line),
+            _ => (line, None),
+        };
+
+        if !line.is_empty() || event.is_none() {
+            if stream == OutputStream::Stderr {
+                stderr.push_str(line);
+                stderr.push('\n');
+            }
+            if let Some((name, _)) = &running {
+                if let Some(run) = runs.iter_mut().rev().find(|r| &r.0 == name) {
+                    run.2.push_str(line);
+                    run.2.push('\n');
+                }
+            }
+            on_output(HookOutput {
+                hook: running.as_ref().map(|(name, _)| name.clone()),
+                stream,
+                line: line.to_string(),
+            });
+        }
+
+        if let Some(event) = event {
+            track_hook_event(&event, &mut running, &mut runs);
+        }
+    })?;
+
+    if result.exit_code != 0 {
+        if let Some((hook, exit_code, output)) = runs.into_iter().find(|r| r.1 != 0) {
+            return Err(GitError::HookFailed { hook, exit_code, output });
+        }
+    }
+
+    Ok(CommandResult {
+        stdout: result.stdout,
+        stderr,
+        exit_code: result.exit_code,
+    })
+}
+
+/// Split a stderr line into the output before a trace2 event, possibly
+/// empty, and the event
+fn split_trace_event(line: &str) -> (&str, Option<serde_json::Value>) {
+    let Some(start) = line.find("{\"event\":") else {
+        return (line, None);
+    };
+    match serde_json::from_str(&line[start..]) {
+        Ok(event) => (&line[..start], Some(event)),
+        Err(_) => (line, None),
+    }
+}
+
+/// Follow the start and exit of hooks run by the git process itself; events
+/// of git commands run from hooks have a nested session id and are skipped
+fn track_hook_event(event: &serde_json::Value, running: &mut Option<(String, u64)>, runs: &mut Vec<(String, i32, String)>) {
+    if event["sid"].as_str().is_none_or(|sid| sid.contains('/')) {
+        return;
+    }
+
+    match event["event"].as_str() {
+        Some("child_start") if event["child_class"] == "hook" => {
+            let name = event["hook_name"].as_str().unwrap_or_default().to_string();
+            runs.push((name.clone(), 0, String::new()));
+            *running = Some((name, event["child_id"].as_u64().unwrap_or_default()));
+        }
+        Some("child_exit") => {
+            let Some((name, child_id)) = running.take() else {
+                return;
+            };
+            if event["child_id"].as_u64() != Some(child_id) {
+                *running = Some((name, child_id));
+                return;
+            }
+            if let Some(run) = runs.iter_mut().rev().find(|r| r.0 == name) {
+                run.1 = event["code"].as_i64().unwrap_or(-1) as i32;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// List the hooks installed in the hooks directory (core.hooksPath or
+/// .git/hooks), leaving out the .sample files git creates
+pub fn list_hooks(executor: &GitExecutor) -> Result<Vec<HookInfo>, GitError> {
+    let dir = hooks_dir(executor)?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut hooks: Vec<HookInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let (name, disabled) = match file_name.strip_suffix(DISABLED_SUFFIX) {
+                Some(name) => (name.to_string(), true),
+                None => (file_name, false),
+            };
+            if !KNOWN_HOOKS.contains(&name.as_str()) || !entry.path().is_file() {
+                return None;
+            }
+            Some(HookInfo {
+                name,
+                enabled: !disabled && is_executable(&entry.path()),
+                path: entry.path().to_string_lossy().to_string(),
+            })
+        })
+        .collect();
+
+    hooks.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(hooks)
+}
+
+/// Enable or disable an installed hook. Disabled hooks are renamed with a
+/// ".disabled" suffix so git no longer finds them.
+pub fn set_hook_enabled(executor: &GitExecutor, name: &str, enabled: bool) -> Result<(), GitError> {
+    if !KNOWN_HOOKS.contains(&name) {
+        return Err(GitError::ParseError {
+            message: format!("Unknown hook: {}", name),
+        });
+    }
+
+    let dir = hooks_dir(executor)?;
+    let active = dir.join(name);
+    let disabled = dir.join(format!("{}{}", name, DISABLED_SUFFIX));
+    let io_error = |e: std::io::Error| GitError::IoError {
+        message: format!("Failed to update hook {}: {}", name, e),
+    };
+
+    if enabled {
+        if disabled.is_file() && !active.exists() {
+            fs::rename(&disabled, &active).map_err(io_error)?;
+        }
+        if !active.is_file() {
+            return Err(GitError::IoError {
+                message: format!("Hook {} is not installed", name),
+            });
+        }
+        make_executable(&active).map_err(io_error)?;
+    } else if active.is_file() {
+        fs::rename(&active, &disabled).map_err(io_error)?;
+    }
+
+    Ok(())
+}
+
+fn hooks_dir(executor: &GitExecutor) -> Result<PathBuf, GitError> {
+    let dir = executor.execute_checked(&["rev-parse", "--git-path", "hooks"])?;
+    Ok(Path::new(executor.repo_path()).join(dir.trim()))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use crate::git::commit::{create_commit, CommitOptions};
+
+    #[test]
+    fn reports_failing_hooks_with_their_output() {
+        let (_dir, executor) = init_repo();
+        let hooks = hooks_dir(&executor).unwrap();
+        fs::write(hooks.join("pre-commit"), "#!/bin/sh\necho checking\nexit 0\n").unwrap();
+        fs::write(hooks.join("commit-msg"), "#!/bin/sh\necho lint failed >&2\nexit 3\n").unwrap();
+        set_hook_enabled(&executor, "pre-commit", true).unwrap();
+        set_hook_enabled(&executor, "commit-msg", true).unwrap();
+
+        let options = CommitOptions {
+            allow_empty: true,
+            ..Default::default()
+        };
+        let mut lines = Vec::new();
+        let error = create_commit(&executor, "bad", &options, |output| lines.push(output)).unwrap_err();
+        match error {
+            GitError::HookFailed { hook, exit_code, output } => {
+                assert_eq!((hook.as_str(), exit_code), ("commit-msg", 3));
+                assert_eq!(output, "lint failed\n");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        let summary: Vec<(Option<&str>, &str)> = lines.iter().map(|l| (l.hook.as_deref(), l.line.as_str())).collect();
+        assert_eq!(summary, vec![(Some("pre-commit"), "checking"), (Some("commit-msg"), "lint failed")]);
+
+        let listed = list_hooks(&executor).unwrap();
+        let names: Vec<(&str, bool)> = listed.iter().map(|h| (h.name.as_str(), h.enabled)).collect();
+        assert_eq!(names, vec![("commit-msg", true), ("pre-commit", true)]);
+
+        set_hook_enabled(&executor, "commit-msg", false).unwrap();
+        assert!(!list_hooks(&executor).unwrap()[0].enabled);
+        create_commit(&executor, "good", &options, |_| {}).unwrap();
+
+        // Output without a trailing newline shares its line with the next event
+        fs::write(hooks.join("pre-commit"), "#!/bin/sh\nprintf 'no newline' >&2\nexit 1\n").unwrap();
+        let mut lines = Vec::new();
+        let error = create_commit(&executor, "bad", &options, |output| lines.push(output)).unwrap_err();
+        match error {
+            GitError::HookFailed { hook, exit_code, output } => {
+                assert_eq!((hook.as_str(), exit_code), ("pre-commit", 1));
+                assert_eq!(output, "no newline\n");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(lines.iter().all(|l| !l.line.contains("{\"event\":")));
+        assert_eq!(lines[0].line, "no newline");
+    }
+}
@@ -1,27 +1,321 @@
+use serde::{Deserialize, Serialize};
+
 use crate::git::error::GitError;
 use crate::git::executor::GitExecutor;
-use crate::git::types::Commit;
+use crate::git::notes::get_commit_notes;
+use crate::git::types::{
+    Commit, CommitDetails, CommitFileChange, CommitStats, FileLogEntry, FileStatus, Trailer,
+};
 
 // Format: hash, short_hash, author_name, author_email, author_date, author_timestamp,
 //         committer_name, committer_email, committer_date, committer_timestamp,
-//         subject, body, parents, refs
-const LOG_FORMAT: &str = "%H%x00%h%x00%an%x00%ae%x00%aI%x00%at%x00%cn%x00%ce%x00%cI%x00%ct%x00%s%x00%b%x00%P%x00%D%x00---END---";
+//         subject, body, parents, refs, trailers,
+//         raw author_name, raw author_email, raw committer_name, raw committer_email
+// Names and emails are mailmapped (%aN), the raw ones are kept as recorded (%an).
+// Trailers are unfolded and separated by \x1e, with \x1f between key and value
+const LOG_FORMAT: &str = "%H%x00%h%x00%aN%x00%aE%x00%aI%x00%at%x00%cN%x00%cE%x00%cI%x00%ct%x00%s%x00%b%x00%P%x00%D%x00%(trailers:only,unfold,separator=%x1e,key_value_separator=%x1f)%x00%an%x00%ae%x00%cn%x00%ce%x00---END---";
+
+/// Filters for `get_commit_log`, all optional and combined with AND
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    /// Patterns matched against "Name <email>" of the author or committer,
+    /// as basic regexes when a message is searched, extended ones with
+    /// `message_regex`, and as grep.patternType says otherwise
+    pub author: Option<String>,
+    pub committer: Option<String>,
+    /// Committer date bounds in any format git accepts ("2024-01-31", "2 weeks ago")
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Searched in the commit message, as a fixed string unless `message_regex`
+    pub message: Option<String>,
+    /// Read `message` as an extended regex. git has a single regex flavor
+    /// per command, so the author and committer patterns are then extended
+    /// regexes too.
+    pub message_regex: bool,
+    /// Applies to the author, committer and message patterns
+    pub ignore_case: bool,
+    /// Commits changing the number of occurrences of this string (`-S`),
+    /// or with added/removed lines matching it when `pickaxe_regex` (`-G`)
+    pub pickaxe: Option<String>,
+    pub pickaxe_regex: bool,
+    /// Only commits touching these paths
+    pub paths: Vec<String>,
+    pub merges: MergeFilter,
+    pub first_parent: bool,
+    pub refs: RefSelection,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeFilter {
+    #[default]
+    All,
+    MergesOnly,
+    NoMerges,
+}
 
+/// Which refs the history is walked from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum RefSelection {
+    /// Every ref, optionally leaving out remote-tracking branches
+    All { exclude_remotes: bool },
+    /// HEAD only
+    Current,
+    /// The given branches, tags or revisions
+    Selected { refs: Vec<String> },
+}
+
+impl LogQuery {
+    /// Whether every parent of a listed commit is listed too, once parents
+    /// are rewritten for the paths. git only rewrites parents past commits
+    /// left out by paths, not by authors, dates, messages or merges.
+    pub fn keeps_ancestry(&self) -> bool {
+        self.author.is_none()
+            && self.committer.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.message.is_none()
+            && self.pickaxe.is_none()
+            && self.merges == MergeFilter::All
+    }
+
+    /// True for the plain `--all` history, which is served from the commit cache
+    pub fn is_unfiltered(&self) -> bool {
+        self.author.is_none()
+            && self.committer.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.message.is_none()
+            && self.pickaxe.is_none()
+            && self.paths.is_empty()
+            && self.merges == MergeFilter::All
+            && !self.first_parent
+            && matches!(self.refs, RefSelection::All { exclude_remotes: false })
+    }
+}
+
+impl Default for RefSelection {
+    fn default() -> Self {
+        RefSelection::All { exclude_remotes: false }
+    }
+}
+
+/// Get a page of the log. `with_stats` adds the number of files changed,
+/// insertions and deletions of each commit, merges counted against their
+/// first parent.
 pub fn get_commit_log(
     executor: &GitExecutor,
+    query: &LogQuery,
     count: u32,
     skip: u32,
+    with_stats: bool,
 ) -> Result<Vec<Commit>, GitError> {
+    if !with_stats {
+        let args = log_query_args(query, LOG_FORMAT, Some((count, skip)))?;
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let output = executor.execute_checked(&args)?;
+        return parse_log(&output);
+    }
+
+    // The stats are printed after the formatted commit, so each entry starts
+    // with a marker line to keep them attached to it
+    let mut args = log_query_args(query, &format!("---COMMIT---%n{}", LOG_FORMAT), Some((count, skip)))?;
+    let separator = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    args.splice(separator..separator, ["--shortstat".to_string(), "--diff-merges=first-parent".to_string()]);
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let output = executor.execute_checked(&args)?;
+
+    let mut commits = Vec::new();
+    for chunk in output.split("---COMMIT---\n").filter(|s| !s.trim().is_empty()) {
+        let (format, stats) = chunk.split_once("---END---").unwrap_or((chunk, ""));
+        if let Some(commit) = parse_commit(format) {
+            commits.push(Commit {
+                stats: Some(parse_shortstat(stats)),
+                ..commit
+            });
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Parse " 3 files changed, 10 insertions(+), 2 deletions(-)"; an empty
+/// line means nothing changed
+fn parse_shortstat(text: &str) -> CommitStats {
+    let mut stats = CommitStats::default();
+    for part in text.trim().split(", ") {
+        let mut words = part.split_whitespace();
+        let count = words.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        match words.next() {
+            Some(w) if w.starts_with("file") => stats.files_changed = count,
+            Some(w) if w.starts_with("insertion") => stats.insertions = count,
+            Some(w) if w.starts_with("deletion") => stats.deletions = count,
+            _ => {}
+        }
+    }
+    stats
+}
+
+/// Get the given commits, in the given order
+pub fn get_commits(executor: &GitExecutor, hashes: &[String]) -> Result<Vec<Commit>, GitError> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Hashes go through stdin so any number of them fits
+    let format = format!("--format={}", LOG_FORMAT);
+    let input: String = hashes.iter().map(|h| format!("{}\n", h)).collect();
+    let output = executor.execute_with_stdin(
+        &["log", "--no-walk=unsorted", "--stdin", "--decorate=full", &format, "--"],
+        &input,
+    )?;
+    parse_log(&output)
+}
+
+/// Run the log of `query` printing `format` for every commit, without paging
+pub fn stream_commit_log<F>(executor: &GitExecutor, query: &LogQuery, format: &str, on_line: F) -> Result<(), GitError>
+where
+    F: FnMut(&str),
+{
+    let args = log_query_args(query, format, None)?;
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    executor.execute_streaming(&args, on_line)
+}
+
+/// Get only the hashes and parent hashes of the commits `get_commit_log`
+/// would return, in the same order. When paths limit the log, parents are
+/// rewritten to the nearest ancestors that are part of it.
+pub fn get_commit_parents(
+    executor: &GitExecutor,
+    query: &LogQuery,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<(String, Vec<String>)>, GitError> {
+    let mut args = log_query_args(query, "%H %P", Some((count, skip)))?;
+    if !query.paths.is_empty() {
+        args.insert(1, "--parents".into());
+    }
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let output = executor.execute_checked(&args)?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut hashes = line.split_whitespace().map(|h| h.to_string());
+            Some((hashes.next()?, hashes.collect()))
+        })
+        .collect())
+}
+
+/// Build the `git log` arguments of a query, `page` being (count, skip)
+fn log_query_args(query: &LogQuery, format: &str, page: Option<(u32, u32)>) -> Result<Vec<String>, GitError> {
+    let mut args: Vec<String> = vec![
+        "log".into(),
+        "--topo-order".into(),
+        "--decorate=full".into(),
+        format!("--format={}", format),
+    ];
+    if let Some((count, skip)) = page {
+        args.extend(["-n".into(), count.to_string(), "--skip".into(), skip.to_string()]);
+    }
+
+    if let Some(ref author) = query.author {
+        args.push(format!("--author={}", author));
+    }
+    if let Some(ref committer) = query.committer {
+        args.push(format!("--committer={}", committer));
+    }
+    if let Some(ref since) = query.since {
+        args.push(format!("--since={}", since));
+    }
+    if let Some(ref until) = query.until {
+        args.push(format!("--until={}", until));
+    }
+    if let Some(ref message) = query.message {
+        // The regex flavor applies to the author and committer patterns
+        // too: --fixed-strings would make them fixed strings, so a fixed
+        // message is escaped as a basic regex instead, whatever
+        // grep.patternType says
+        if query.message_regex {
+            args.push(format!("--grep={}", message));
+            args.push("--extended-regexp".into());
+        } else {
+            args.push(format!("--grep={}", escape_basic_regex(message)));
+            args.push("--basic-regexp".into());
+        }
+    }
+    if query.ignore_case {
+        args.push("--regexp-ignore-case".into());
+    }
+    if let Some(ref pickaxe) = query.pickaxe {
+        let flag = if query.pickaxe_regex { "-G" } else { "-S" };
+        args.push(format!("{}{}", flag, pickaxe));
+    }
+    match query.merges {
+        MergeFilter::All => {}
+        MergeFilter::MergesOnly => args.push("--merges".into()),
+        MergeFilter::NoMerges => args.push("--no-merges".into()),
+    }
+    if query.first_parent {
+        args.push("--first-parent".into());
+    }
+
+    match query.refs {
+        RefSelection::All { exclude_remotes } => {
+            // --exclude only applies to the --all that follows it
+            if exclude_remotes {
+                args.push("--exclude=refs/remotes/*".into());
+            }
+            args.push("--all".into());
+        }
+        RefSelection::Current => args.push("HEAD".into()),
+        RefSelection::Selected { ref refs } => {
+            // Never let a ref be read as an option
+            if let Some(option) = refs.iter().find(|r| r.starts_with('-')) {
+                return Err(GitError::RevisionNotFound {
+                    revision: option.clone(),
+                });
+            }
+            args.extend(refs.iter().cloned());
+        }
+    }
+
+    args.push("--".into());
+    args.extend(query.paths.iter().cloned());
+    Ok(args)
+}
+
+/// Escape a string so a basic regex (git's default) matches it literally
+fn escape_basic_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '.' | '[' | ']' | '*' | '^' | '$' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Get the commits of a revision range such as `a..b`, newest first
+pub fn get_range_log(executor: &GitExecutor, range: &str, count: u32) -> Result<Vec<Commit>, GitError> {
+    // Never let the range be read as an option
+    if range.starts_with('-') {
+        return Err(GitError::RevisionNotFound {
+            revision: range.to_string(),
+        });
+    }
+
     let output = executor.execute_checked(&[
         "log",
-        "--all",
         "--topo-order",
-        "--decorate=full",
         &format!("--format={}", LOG_FORMAT),
         "-n",
         &count.to_string(),
-        "--skip",
-        &skip.to_string(),
+        range,
+        "--",
     ])?;
 
     parse_log(&output)
@@ -31,39 +325,457 @@ fn parse_log(output: &str) -> Result<Vec<Commit>, GitError> {
     let mut commits = Vec::new();
 
     for entry in output.split("---END---").filter(|s| !s.trim().is_empty()) {
-        let parts: Vec<&str> = entry.trim().split('\0').collect();
-        if parts.len() < 14 {
+        if let Some(commit) = parse_commit(entry) {
+            commits.push(commit);
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Parse one LOG_FORMAT entry (without its ---END--- marker)
+fn parse_commit(entry: &str) -> Option<Commit> {
+    let parts: Vec<&str> = entry.trim().split('\0').collect();
+    if parts.len() < 14 {
+        return None;
+    }
+
+    let parent_hashes: Vec<String> = parts[12]
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect();
+
+    let refs: Vec<String> = parts[13]
+        .split(", ")
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim().to_string())
+        .collect();
+
+    Some(Commit {
+        hash: parts[0].to_string(),
+        short_hash: parts[1].to_string(),
+        author_name: parts[2].to_string(),
+        author_email: parts[3].to_string(),
+        author_date: parts[4].to_string(),
+        author_timestamp: parts[5].parse().unwrap_or(0),
+        committer_name: parts[6].to_string(),
+        committer_email: parts[7].to_string(),
+        committer_date: parts[8].to_string(),
+        committer_timestamp: parts[9].parse().unwrap_or(0),
+        subject: parts[10].to_string(),
+        body: parts[11].to_string(),
+        parent_hashes,
+        refs,
+        signature: None,
+        trailers: parts.get(14).map(|t| parse_trailers(t)).unwrap_or_default(),
+        stats: None,
+        raw_author_name: parts.get(15).unwrap_or(&parts[2]).to_string(),
+        raw_author_email: parts.get(16).unwrap_or(&parts[3]).to_string(),
+        raw_committer_name: parts.get(17).unwrap_or(&parts[6]).to_string(),
+        raw_committer_email: parts.get(18).unwrap_or(&parts[7]).to_string(),
+    })
+}
+
+/// Parse the trailers placeholder of LOG_FORMAT
+fn parse_trailers(field: &str) -> Vec<Trailer> {
+    field
+        .split('\x1e')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('\x1f')?;
+            Some(Trailer {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Get the history of a single file, following renames (`git log --follow`)
+pub fn get_file_log(
+    executor: &GitExecutor,
+    path: &str,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<FileLogEntry>, GitError> {
+    // Each entry starts with a marker line so the diff stats printed after
+    // the formatted commit stay attached to it
+    let output = executor.execute_checked(&[
+        "log",
+        "--follow",
+        "--decorate=full",
+        &format!("--format=---COMMIT---%n{}", LOG_FORMAT),
+        "--raw",
+        "--numstat",
+        "-n",
+        &count.to_string(),
+        "--skip",
+        &skip.to_string(),
+        "--",
+        path,
+    ])?;
+
+    parse_file_log(&output, path)
+}
+
+fn parse_file_log(output: &str, path: &str) -> Result<Vec<FileLogEntry>, GitError> {
+    let mut entries = Vec::new();
+    // Path of the file in the commit being parsed, updated as renames are crossed
+    let mut current_path = path.to_string();
+
+    for chunk in output.split("---COMMIT---\n").filter(|s| !s.trim().is_empty()) {
+        let (format, stats) = chunk.split_once("---END---").unwrap_or((chunk, ""));
+        let commit = match parse_commit(format) {
+            Some(commit) => commit,
+            None => continue,
+        };
+
+        let mut entry = FileLogEntry {
+            commit,
+            path: current_path.clone(),
+            status: FileStatus::Modified,
+            additions: 0,
+            deletions: 0,
+        };
+
+        for line in stats.lines() {
+            if let Some(raw) = line.strip_prefix(':') {
+                if let Some((status, new_path)) = parse_raw_line(raw) {
+                    if let FileStatus::Renamed { ref from } | FileStatus::Copied { ref from } = status {
+                        current_path = from.clone();
+                    }
+                    entry.path = new_path;
+                    entry.status = status;
+                }
+            } else {
+                // "<added>\t<deleted>\t<path>", "-" for binary files
+                let mut fields = line.split('\t');
+                if let (Some(added), Some(deleted), Some(_)) = (fields.next(), fields.next(), fields.next()) {
+                    entry.additions += added.parse().unwrap_or(0);
+                    entry.deletions += deleted.parse().unwrap_or(0);
+                }
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Parse a `--raw` line (without its leading ':') into the change status and
+/// the path of the file after the change:
+/// "<old mode> <new mode> <old oid> <new oid> <status>\t<path>[\t<new path>]"
+fn parse_raw_line(raw: &str) -> Option<(FileStatus, String)> {
+    let mut fields = raw.split('\t');
+    let status = fields.next().and_then(|f| f.split_whitespace().nth(4)).unwrap_or("M");
+    let first = fields.next().map(unquote_path);
+    let second = fields.next().map(unquote_path);
+
+    match (status.chars().next(), first, second) {
+        (Some('R'), Some(from), Some(to)) => Some((FileStatus::Renamed { from }, to)),
+        (Some('C'), Some(from), Some(to)) => Some((FileStatus::Copied { from }, to)),
+        (Some(c), Some(path), _) => {
+            let status = match c {
+                'A' => FileStatus::Added,
+                'D' => FileStatus::Deleted,
+                'T' => FileStatus::TypeChanged,
+                _ => FileStatus::Modified,
+            };
+            Some((status, path))
+        }
+        _ => None,
+    }
+}
+
+/// Get a commit with the list of files it changes and their line counts,
+/// without loading the hunks. Merges are compared to their first parent.
+pub fn get_commit_details(executor: &GitExecutor, hash: &str) -> Result<CommitDetails, GitError> {
+    let output = executor.execute_checked(&[
+        "show",
+        "--decorate=full",
+        &format!("--format=---COMMIT---%n{}", LOG_FORMAT),
+        "--diff-merges=first-parent",
+        "-M",
+        "--raw",
+        "--numstat",
+        hash,
+        "--",
+    ])?;
+
+    let chunk = output.strip_prefix("---COMMIT---\n").unwrap_or(&output);
+    let (format, stats) = chunk.split_once("---END---").unwrap_or((chunk, ""));
+    let commit = parse_commit(format).ok_or_else(|| GitError::ParseError {
+        message: format!("Unexpected output for commit {}", hash),
+    })?;
+
+    // --raw and --numstat list the files in the same order
+    let mut files: Vec<CommitFileChange> = stats
+        .lines()
+        .filter_map(|line| line.strip_prefix(':'))
+        .filter_map(parse_raw_line)
+        .map(|(status, path)| CommitFileChange {
+            path,
+            status,
+            additions: 0,
+            deletions: 0,
+            is_binary: false,
+        })
+        .collect();
+    let numstats = stats.lines().filter(|l| !l.starts_with(':') && l.contains('\t'));
+    for (file, line) in files.iter_mut().zip(numstats) {
+        let mut fields = line.split('\t');
+        let added = fields.next().unwrap_or("-");
+        let deleted = fields.next().unwrap_or("-");
+        file.is_binary = added == "-" && deleted == "-";
+        file.additions = added.parse().unwrap_or(0);
+        file.deletions = deleted.parse().unwrap_or(0);
+    }
+
+    let stats = CommitStats {
+        files_changed: files.len() as u32,
+        insertions: files.iter().map(|f| f.additions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+    };
+
+    Ok(CommitDetails {
+        commit: Commit {
+            stats: Some(stats),
+            ..commit
+        },
+        files,
+        notes: get_commit_notes(executor, hash, &[])?,
+    })
+}
+
+/// Undo git's C-style quoting of paths containing special characters
+pub fn unquote_path(path: &str) -> String {
+    let inner = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(inner) => inner,
+        None => return path.to_string(),
+    };
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
             continue;
         }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(b'a') => bytes.push(0x07),
+            Some(b'b') => bytes.push(0x08),
+            Some(b'f') => bytes.push(0x0C),
+            Some(b'v') => bytes.push(0x0B),
+            Some(d @ b'0'..=b'7') => {
+                // Octal escape of a raw byte, e.g. "\303\251"
+                let mut value = (d - b'0') as u32;
+                for _ in 0..2 {
+                    match chars.peek() {
+                        Some(&o @ b'0'..=b'7') => {
+                            value = value * 8 + (o - b'0') as u32;
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
 
-        let parent_hashes: Vec<String> = parts[12]
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
+    String::from_utf8_lossy(&bytes).to_string()
+}
 
-        let refs: Vec<String> = parts[13]
-            .split(", ")
-            .filter(|s| !s.is_empty())
-            .map(|s| s.trim().to_string())
-            .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use std::fs;
 
-        commits.push(Commit {
-            hash: parts[0].to_string(),
-            short_hash: parts[1].to_string(),
-            author_name: parts[2].to_string(),
-            author_email: parts[3].to_string(),
-            author_date: parts[4].to_string(),
-            author_timestamp: parts[5].parse().unwrap_or(0),
-            committer_name: parts[6].to_string(),
-            committer_email: parts[7].to_string(),
-            committer_date: parts[8].to_string(),
-            committer_timestamp: parts[9].parse().unwrap_or(0),
-            subject: parts[10].to_string(),
-            body: parts[11].to_string(),
-            parent_hashes,
-            refs,
-        });
+    #[test]
+    fn commit_log_query_filters_combine() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+
+        fs::write(dir.path().join("a.txt"), "alpha\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "Add alpha"]);
+        git(&["checkout", "-q", "-b", "topic"]);
+        fs::write(dir.path().join("b.txt"), "beta\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "--author", "Other <other@example.com>", "-m", "Add beta (fix)"]);
+        git(&["checkout", "-q", "main"]);
+        fs::write(dir.path().join("a.txt"), "alpha\ngamma\n").unwrap();
+        git(&["commit", "-q", "-am", "Extend alpha"]);
+        git(&["merge", "-q", "--no-ff", "-m", "Merge topic", "topic"]);
+
+        let subjects = |query: LogQuery| -> Vec<String> {
+            get_commit_log(&executor, &query, 50, 0, false).unwrap().into_iter().map(|c| c.subject).collect()
+        };
+
+        assert_eq!(subjects(LogQuery::default()).len(), 4);
+        assert_eq!(
+            subjects(LogQuery {
+                merges: MergeFilter::MergesOnly,
+                ..Default::default()
+            }),
+            vec!["Merge topic"]
+        );
+        assert_eq!(
+            subjects(LogQuery {
+                first_parent: true,
+                merges: MergeFilter::NoMerges,
+                refs: RefSelection::Current,
+                ..Default::default()
+            }),
+            vec!["Extend alpha", "Add alpha"]
+        );
+        // The message is a fixed string, so the parentheses are literal
+        assert_eq!(
+            subjects(LogQuery {
+                message: Some("BETA (FIX)".into()),
+                ignore_case: true,
+                ..Default::default()
+            }),
+            vec!["Add beta (fix)"]
+        );
+        assert!(subjects(LogQuery {
+            author: Some("other@".into()),
+            message: Some("alpha".into()),
+            ..Default::default()
+        })
+        .is_empty());
+        // A fixed message leaves the author a pattern
+        let fixed = |message: &str| LogQuery {
+            author: Some("oth.r".into()),
+            message: Some(message.into()),
+            ..Default::default()
+        };
+        assert_eq!(subjects(fixed("beta (fix)")), vec!["Add beta (fix)"]);
+        assert!(subjects(fixed("beta.*")).is_empty());
+        // ...whatever regex flavor the configuration picks
+        git(&["config", "grep.patternType", "extended"]);
+        assert_eq!(subjects(fixed("beta (fix)")), vec!["Add beta (fix)"]);
+        git(&["config", "--unset", "grep.patternType"]);
+        assert_eq!(
+            subjects(LogQuery {
+                message_regex: true,
+                ..fixed("beta \\((fix|feat)\\)$")
+            }),
+            vec!["Add beta (fix)"]
+        );
+        let option = LogQuery {
+            refs: RefSelection::Selected {
+                refs: vec!["--output=x".into()],
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_commit_log(&executor, &option, 50, 0, false),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+        assert!(matches!(
+            get_range_log(&executor, "--output=x", 50),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+        assert_eq!(
+            subjects(LogQuery {
+                pickaxe: Some("gamma".into()),
+                paths: vec!["a.txt".into()],
+                refs: RefSelection::Selected { refs: vec!["main".into()] },
+                ..Default::default()
+            }),
+            vec!["Extend alpha"]
+        );
+        assert_eq!(
+            subjects(LogQuery {
+                refs: RefSelection::Selected { refs: vec!["topic".into()] },
+                ..Default::default()
+            }),
+            vec!["Add beta (fix)", "Add alpha"]
+        );
     }
 
-    Ok(commits)
+    #[test]
+    fn commit_stats_and_details() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+
+        fs::write(dir.path().join("a.txt"), "a\nb\nc\nd\n").unwrap();
+        fs::write(dir.path().join("logo.bin"), b"\0\x01").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "create"]);
+        git(&["mv", "a.txt", "b.txt"]);
+        fs::write(dir.path().join("b.txt"), "a\nb\nc\nd\ne\n").unwrap();
+        fs::write(dir.path().join("c.txt"), "new\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "rename and add"]);
+
+        let log = get_commit_log(&executor, &LogQuery::default(), 10, 0, true).unwrap();
+        let stats: Vec<CommitStats> = log.iter().map(|c| c.stats.clone().unwrap()).collect();
+        assert_eq!(stats[0], CommitStats { files_changed: 2, insertions: 2, deletions: 0 });
+        assert_eq!(stats[1], CommitStats { files_changed: 2, insertions: 4, deletions: 0 });
+
+        let details = get_commit_details(&executor, &log[0].hash).unwrap();
+        assert_eq!(details.commit.subject, "rename and add");
+        let files: Vec<(&str, &FileStatus, u32)> =
+            details.files.iter().map(|f| (f.path.as_str(), &f.status, f.additions)).collect();
+        assert_eq!(
+            files,
+            vec![
+                ("b.txt", &FileStatus::Renamed { from: "a.txt".into() }, 1),
+                ("c.txt", &FileStatus::Added, 1),
+            ]
+        );
+
+        let details = get_commit_details(&executor, &log[1].hash).unwrap();
+        assert!(details.files.iter().any(|f| f.path == "logo.bin" && f.is_binary));
+    }
+
+    #[test]
+    fn parses_trailers() {
+        let field = "Signed-off-by\x1fJane <jane@example.com>\x1eFixes\x1f#42\x1eChange-Id\x1fI0123";
+        let trailers = parse_trailers(field);
+        let keys: Vec<&str> = trailers.iter().map(|t| t.key.as_str()).collect();
+        assert_eq!(keys, vec!["Signed-off-by", "Fixes", "Change-Id"]);
+        assert_eq!(trailers[0].value, "Jane <jane@example.com>");
+        assert!(parse_trailers("").is_empty());
+    }
+
+    #[test]
+    fn file_log_follows_renames() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+
+        fs::write(dir.path().join("old.txt"), "a\nb\nc\nd\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "create"]);
+        git(&["mv", "old.txt", "new.txt"]);
+        git(&["commit", "-q", "-m", "rename"]);
+        fs::write(dir.path().join("new.txt"), "a\nb\nc\nd\ne\nf\n").unwrap();
+        git(&["commit", "-q", "-am", "extend"]);
+
+        let log = get_file_log(&executor, "new.txt", 10, 0).unwrap();
+        let summary: Vec<(&str, &str, u32)> = log
+            .iter()
+            .map(|e| (e.commit.subject.as_str(), e.path.as_str(), e.additions))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("extend", "new.txt", 2), ("rename", "new.txt", 0), ("create", "old.txt", 4)]
+        );
+        assert_eq!(log[1].status, FileStatus::Renamed { from: "old.txt".to_string() });
+        assert_eq!(log[2].status, FileStatus::Added);
+    }
+
+    #[test]
+    fn unquotes_c_style_paths() {
+        assert_eq!(unquote_path("plain.txt"), "plain.txt");
+        assert_eq!(unquote_path("\"caf\\303\\251 \\\"x\\\".txt\""), "café \"x\".txt");
+    }
 }
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+
+/// One line of .mailmap, mapping the identity recorded in commits to the
+/// proper one. Lines are one of
+/// "Proper Name <commit@email>",
+/// "<proper@email> <commit@email>",
+/// "Proper Name <proper@email> <commit@email>",
+/// "Proper Name <proper@email> Commit Name <commit@email>".
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MailmapEntry {
+    pub proper_name: Option<String>,
+    pub proper_email: Option<String>,
+    /// Only commits with this name are mapped when set
+    pub commit_name: Option<String>,
+    pub commit_email: String,
+}
+
+/// Identities of a commit as recorded, before .mailmap is applied
+#[derive(Debug, Clone, Default)]
+pub struct RawIdentity {
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+}
+
+/// Read the entries of the repository's .mailmap
+pub fn get_mailmap(executor: &GitExecutor) -> Result<Vec<MailmapEntry>, GitError> {
+    let content = read_mailmap_file(executor)?;
+    Ok(content.lines().filter_map(parse_entry).collect())
+}
+
+/// Replace the entries of the repository's .mailmap. Entries take the
+/// place of the existing ones in order, extra ones are added at the end.
+/// Comments, blank lines and lines git would not read stay where they are,
+/// and so do entries left unchanged.
+pub fn set_mailmap(executor: &GitExecutor, entries: &[MailmapEntry]) -> Result<(), GitError> {
+    if entries.iter().any(|entry| entry.commit_email.is_empty()) {
+        return Err(GitError::ParseError {
+            message: "A mailmap entry needs the email used in commits".to_string(),
+        });
+    }
+
+    let existing = read_mailmap_file(executor)?;
+    let mut entries = entries.iter();
+    let mut content = String::new();
+    for line in existing.lines() {
+        match parse_entry(line) {
+            Some(old) => match entries.next() {
+                Some(entry) if *entry == old => content.push_str(line),
+                Some(entry) => content.push_str(&format_entry(entry)),
+                None => continue,
+            },
+            None => content.push_str(line),
+        }
+        content.push('\n');
+    }
+    for entry in entries {
+        content.push_str(&format_entry(entry));
+        content.push('\n');
+    }
+
+    fs::write(mailmap_path(executor)?, content).map_err(|e| GitError::IoError {
+        message: format!("Failed to write .mailmap: {}", e),
+    })
+}
+
+/// Resolve "Name <email>" identities through every configured mailmap
+/// (.mailmap, mailmap.file and mailmap.blob)
+pub fn check_mailmap(executor: &GitExecutor, identities: &[String]) -> Result<Vec<String>, GitError> {
+    if identities.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["check-mailmap"];
+    args.extend(identities.iter().map(|i| i.as_str()));
+    let output = executor.execute_checked(&args)?;
+    Ok(output.lines().map(|l| l.to_string()).collect())
+}
+
+/// Hash of every mailmap source (.mailmap, mailmap.file and mailmap.blob),
+/// which changes whenever identities may resolve differently
+pub fn mailmap_fingerprint(executor: &GitExecutor) -> Result<String, GitError> {
+    // Bare repositories have no .mailmap file, only mailmap.blob
+    let mut sources = match mailmap_path(executor) {
+        Ok(_) => read_mailmap_file(executor)?,
+        Err(_) => String::new(),
+    };
+
+    let file = executor.execute(&["config", "--type=path", "--get", "mailmap.file"])?.stdout;
+    if let Some(file) = file.lines().next().filter(|f| !f.is_empty()) {
+        sources.push('\0');
+        sources.push_str(&fs::read_to_string(Path::new(executor.repo_path()).join(file)).unwrap_or_default());
+    }
+
+    let blob = executor.execute(&["config", "--get", "mailmap.blob"])?.stdout;
+    if let Some(blob) = blob.lines().next().filter(|b| !b.is_empty()) {
+        sources.push('\0');
+        sources.push_str(&executor.execute(&["cat-file", "blob", blob])?.stdout);
+    }
+
+    Ok(format!("{:x}", md5::compute(sources)))
+}
+
+/// Look up the identities recorded in the given commits
+pub fn get_raw_identities(
+    executor: &GitExecutor,
+    hashes: &[String],
+) -> Result<HashMap<String, RawIdentity>, GitError> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let input: String = hashes.iter().map(|h| format!("{}\n", h)).collect();
+    let output = executor.execute_with_stdin(
+        &["log", "--no-walk=unsorted", "--stdin", "--format=%H%x00%an%x00%ae%x00%cn%x00%ce", "--"],
+        &input,
+    )?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\0').collect();
+            if parts.len() < 5 {
+                return None;
+            }
+            Some((
+                parts[0].to_string(),
+                RawIdentity {
+                    author_name: parts[1].to_string(),
+                    author_email: parts[2].to_string(),
+                    committer_name: parts[3].to_string(),
+                    committer_email: parts[4].to_string(),
+                },
+            ))
+        })
+        .collect())
+}
+
+fn mailmap_path(executor: &GitExecutor) -> Result<PathBuf, GitError> {
+    let toplevel = executor.execute_checked(&["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(toplevel.trim()).join(".mailmap"))
+}
+
+fn read_mailmap_file(executor: &GitExecutor) -> Result<String, GitError> {
+    let path = mailmap_path(executor)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(GitError::IoError {
+            message: format!("Failed to read {}: {}", path.display(), e),
+        }),
+    }
+}
+
+/// Parse a .mailmap line, None for comments and blank lines
+fn parse_entry(line: &str) -> Option<MailmapEntry> {
+    if line.trim_start().starts_with('#') {
+        return None;
+    }
+
+    let non_empty = |s: &str| {
+        let s = s.trim();
+        (!s.is_empty()).then(|| s.to_string())
+    };
+
+    let (first_name, rest) = line.split_once('<')?;
+    let (first_email, rest) = rest.split_once('>')?;
+    // Anything after the last email, such as a comment, is ignored by git
+    match rest.split_once('<').and_then(|(name, rest)| Some((name, rest.split_once('>')?.0))) {
+        Some((second_name, second_email)) => Some(MailmapEntry {
+            proper_name: non_empty(first_name),
+            proper_email: non_empty(first_email),
+            commit_name: non_empty(second_name),
+            commit_email: second_email.trim().to_string(),
+        }),
+        None => Some(MailmapEntry {
+            proper_name: non_empty(first_name),
+            proper_email: None,
+            commit_name: None,
+            commit_email: first_email.trim().to_string(),
+        }),
+    }
+}
+
+fn format_entry(entry: &MailmapEntry) -> String {
+    let mut parts = Vec::new();
+    if let Some(name) = &entry.proper_name {
+        parts.push(name.clone());
+    }
+    if let Some(email) = &entry.proper_email {
+        parts.push(format!("<{}>", email));
+    }
+    if let Some(name) = &entry.commit_name {
+        parts.push(name.clone());
+    }
+    parts.push(format!("<{}>", entry.commit_email));
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use crate::git::blame::{blame, BlameOptions};
+    use crate::git::commit_cache::get_cached_commit_log;
+    use crate::git::log::{get_commit_log, LogQuery};
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_every_entry_form() {
+        let entry = |proper_name: Option<&str>, proper_email: Option<&str>, commit_name: Option<&str>, email: &str| {
+            MailmapEntry {
+                proper_name: proper_name.map(|s| s.to_string()),
+                proper_email: proper_email.map(|s| s.to_string()),
+                commit_name: commit_name.map(|s| s.to_string()),
+                commit_email: email.to_string(),
+            }
+        };
+
+        let cases = [
+            ("Jane Doe <jane@old>", entry(Some("Jane Doe"), None, None, "jane@old")),
+            ("<jane@new> <jane@old>", entry(None, Some("jane@new"), None, "jane@old")),
+            ("Jane <jane@new> <jane@old>", entry(Some("Jane"), Some("jane@new"), None, "jane@old")),
+            ("Jane <jane@new> jd <jane@old>", entry(Some("Jane"), Some("jane@new"), Some("jd"), "jane@old")),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_entry(line).as_ref(), Some(&expected));
+            assert_eq!(format_entry(&expected), line);
+        }
+        assert_eq!(parse_entry("# Jane <jane@old>"), None);
+        assert_eq!(parse_entry(""), None);
+    }
+
+    #[test]
+    fn log_and_blame_resolve_identities() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        std::fs::write(dir.path().join("file.txt"), "line\n").unwrap();
+        git(&["add", "file.txt"]);
+        git(&["commit", "-q", "-m", "add", "--author", "jd <jane@old.example>"]);
+
+        // The commit cache picks up identities changed by the mailmap
+        let cache = TempDir::new().unwrap();
+        let cached = get_cached_commit_log(&executor, cache.path(), 1, 0).unwrap();
+        assert_eq!(cached[0].author_name, "jd");
+
+        std::fs::write(
+            dir.path().join(".mailmap"),
+            "# Team identities\nJoe <joe@example.com> # ops\n\nnot an entry\nOld <old@example.com>\n# end\n",
+        )
+        .unwrap();
+        let joe = get_mailmap(&executor).unwrap().remove(0);
+        set_mailmap(
+            &executor,
+            &[
+                joe,
+                MailmapEntry {
+                    proper_name: Some("Jane Doe".into()),
+                    proper_email: Some("jane@example.com".into()),
+                    commit_name: None,
+                    commit_email: "jane@old.example".into(),
+                },
+            ],
+        )
+        .unwrap();
+        let content = std::fs::read_to_string(dir.path().join(".mailmap")).unwrap();
+        assert_eq!(
+            content,
+            "# Team identities\nJoe <joe@example.com> # ops\n\nnot an entry\n\
+             Jane Doe <jane@example.com> <jane@old.example>\n# end\n"
+        );
+        assert_eq!(get_mailmap(&executor).unwrap().len(), 2);
+
+        let cached = get_cached_commit_log(&executor, cache.path(), 1, 0).unwrap();
+        assert_eq!(cached[0].author_name, "Jane Doe");
+
+        let commit = &get_commit_log(&executor, &LogQuery::default(), 1, 0, false).unwrap()[0];
+        assert_eq!((commit.author_name.as_str(), commit.author_email.as_str()), ("Jane Doe", "jane@example.com"));
+        assert_eq!((commit.raw_author_name.as_str(), commit.raw_author_email.as_str()), ("jd", "jane@old.example"));
+        assert_eq!(commit.raw_committer_name, "Grist Test");
+
+        let ranges = blame(&executor, "file.txt", &BlameOptions::default()).unwrap();
+        assert_eq!(ranges[0].author_name, "Jane Doe");
+        assert_eq!(ranges[0].raw_author_email, "jane@old.example");
+
+        let resolved = check_mailmap(&executor, &["jd <jane@old.example>".to_string()]).unwrap();
+        assert_eq!(resolved, vec!["Jane Doe <jane@example.com>".to_string()]);
+    }
+}
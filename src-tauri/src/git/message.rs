@@ -0,0 +1,390 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+
+/// Template file at the root of the repository
+const REPO_TEMPLATE: &str = ".gitmessage";
+/// Directory at the root of the repository holding more templates
+const REPO_TEMPLATE_DIR: &str = ".gitmessage.d";
+
+/// Conventional Commits types accepted when the config lists none
+const DEFAULT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitTemplate {
+    pub name: String,
+    pub path: String,
+    /// File content as written
+    pub content: String,
+    /// Content without the "#" comment lines, ready to edit
+    pub message: String,
+    /// True for the template set with commit.template
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Longest subject line allowed
+    pub max_subject_length: Option<usize>,
+    /// Require an empty line between the subject and the body
+    pub blank_second_line: bool,
+    /// Require "type(scope)!: description" subjects
+    pub conventional: Option<ConventionalConfig>,
+    /// Require a reference made of one of these prefixes and a number,
+    /// e.g. "#" for "#123" or "PROJ-" for "PROJ-42"
+    pub issue_prefixes: Vec<String>,
+    /// Longest body line allowed, lines holding a URL are not checked
+    pub max_body_line_length: Option<usize>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_subject_length: Some(72),
+            blank_second_line: true,
+            conventional: None,
+            issue_prefixes: Vec::new(),
+            max_body_line_length: Some(72),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConventionalConfig {
+    /// Allowed types, the usual feat/fix/docs/... when empty
+    pub types: Vec<String>,
+    pub require_scope: bool,
+    /// Allowed scopes, any when empty
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    EmptyMessage,
+    SubjectLength,
+    BlankSecondLine,
+    ConventionalFormat,
+    ConventionalType,
+    ConventionalScope,
+    IssueReference,
+    BodyLineLength,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LintDiagnostic {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    /// 1-based line of the message
+    pub line: usize,
+    /// 1-based column in characters, when the problem has a position
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// Commit templates: the one set with commit.template first, then
+/// .gitmessage and the files of .gitmessage.d at the root of the repository
+pub fn get_commit_templates(executor: &GitExecutor) -> Result<Vec<CommitTemplate>, GitError> {
+    let toplevel = executor.execute_checked(&["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(toplevel.trim());
+
+    let mut paths: Vec<(PathBuf, bool)> = Vec::new();
+    let configured = executor.execute(&["config", "--path", "--get", "commit.template"])?;
+    if configured.exit_code == 0 && !configured.stdout.trim().is_empty() {
+        // Relative paths are taken from the root of the working tree, as git does
+        paths.push((toplevel.join(configured.stdout.trim()), true));
+    }
+    paths.push((toplevel.join(REPO_TEMPLATE), false));
+    if let Ok(entries) = fs::read_dir(toplevel.join(REPO_TEMPLATE_DIR)) {
+        let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect();
+        files.sort();
+        paths.extend(files.into_iter().map(|p| (p, false)));
+    }
+
+    let mut templates: Vec<CommitTemplate> = Vec::new();
+    for (path, is_default) in paths {
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        if templates.iter().any(|t| Path::new(&t.path) == path) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        templates.push(CommitTemplate {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.to_string_lossy().to_string(),
+            message: strip_comments(&content),
+            content,
+            is_default,
+        });
+    }
+
+    Ok(templates)
+}
+
+/// Drop "#" comment lines and surrounding blank lines
+fn strip_comments(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().filter(|l| !l.starts_with('#')).collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Check a commit message against the configured rules
+pub fn lint_commit_message(message: &str, config: &LintConfig) -> Vec<LintDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let subject = lines.first().copied().unwrap_or_default();
+    let diagnostic = |rule, severity, line, column, message: String| LintDiagnostic {
+        rule,
+        severity,
+        line,
+        column,
+        message,
+    };
+
+    if subject.trim().is_empty() {
+        diagnostics.push(diagnostic(
+            LintRule::EmptyMessage,
+            LintSeverity::Error,
+            1,
+            None,
+            "The subject line is empty".to_string(),
+        ));
+        return diagnostics;
+    }
+
+    if let Some(max) = config.max_subject_length {
+        let length = subject.chars().count();
+        if length > max {
+            diagnostics.push(diagnostic(
+                LintRule::SubjectLength,
+                LintSeverity::Error,
+                1,
+                Some(max + 1),
+                format!("The subject is {} characters long, the limit is {}", length, max),
+            ));
+        }
+    }
+
+    if config.blank_second_line && lines.get(1).is_some_and(|l| !l.trim().is_empty()) {
+        diagnostics.push(diagnostic(
+            LintRule::BlankSecondLine,
+            LintSeverity::Error,
+            2,
+            None,
+            "Leave the second line empty to separate the subject from the body".to_string(),
+        ));
+    }
+
+    // Commits made by git itself keep their own subjects
+    let generated = ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|prefix| subject.starts_with(prefix));
+    if let Some(conventional) = config.conventional.as_ref().filter(|_| !generated) {
+        lint_conventional(subject, conventional, &mut diagnostics);
+    }
+
+    if !config.issue_prefixes.is_empty() && !has_issue_reference(message, &config.issue_prefixes) {
+        diagnostics.push(diagnostic(
+            LintRule::IssueReference,
+            LintSeverity::Error,
+            1,
+            None,
+            format!("Reference an issue, e.g. {}123", config.issue_prefixes[0]),
+        ));
+    }
+
+    if let Some(max) = config.max_body_line_length {
+        for (index, line) in lines.iter().enumerate().skip(2) {
+            let length = line.chars().count();
+            if length > max && !line.contains("://") {
+                diagnostics.push(diagnostic(
+                    LintRule::BodyLineLength,
+                    LintSeverity::Warning,
+                    index + 1,
+                    Some(max + 1),
+                    format!("Line is {} characters long, wrap the body at {}", length, max),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Check "type(scope)!: description"
+fn lint_conventional(subject: &str, config: &ConventionalConfig, diagnostics: &mut Vec<LintDiagnostic>) {
+    let format_error = |column: usize, message: &str| LintDiagnostic {
+        rule: LintRule::ConventionalFormat,
+        severity: LintSeverity::Error,
+        line: 1,
+        column: Some(column),
+        message: message.to_string(),
+    };
+
+    let type_end = subject
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(subject.len());
+    let commit_type = &subject[..type_end];
+    let mut rest = &subject[type_end..];
+
+    let mut scope = None;
+    if let Some(after) = rest.strip_prefix('(') {
+        let Some(close) = after.find(')') else {
+            diagnostics.push(format_error(
+                type_end + 1,
+                "The scope is missing its closing parenthesis",
+            ));
+            return;
+        };
+        scope = Some(&after[..close]);
+        rest = &after[close + 1..];
+    }
+    rest = rest.strip_prefix('!').unwrap_or(rest);
+
+    let column = subject.len() - rest.len() + 1;
+    let Some(description) = rest.strip_prefix(": ") else {
+        diagnostics.push(format_error(column, "Expected \"type(scope): description\""));
+        return;
+    };
+    if description.trim().is_empty() {
+        diagnostics.push(format_error(column + 2, "The description is empty"));
+    }
+
+    let allowed_type = if config.types.is_empty() {
+        DEFAULT_TYPES.contains(&commit_type)
+    } else {
+        config.types.iter().any(|t| t == commit_type)
+    };
+    if !allowed_type {
+        diagnostics.push(LintDiagnostic {
+            rule: LintRule::ConventionalType,
+            severity: LintSeverity::Error,
+            line: 1,
+            column: Some(1),
+            message: format!("Unknown commit type \"{}\"", commit_type),
+        });
+    }
+
+    let scope_error = match scope {
+        Some("") => Some("The scope is empty".to_string()),
+        Some(scope) if !config.scopes.is_empty() && !config.scopes.iter().any(|s| s == scope) => {
+            Some(format!("Unknown scope \"{}\"", scope))
+        }
+        None if config.require_scope => Some("A scope is required".to_string()),
+        _ => None,
+    };
+    if let Some(message) = scope_error {
+        diagnostics.push(LintDiagnostic {
+            rule: LintRule::ConventionalScope,
+            severity: LintSeverity::Error,
+            line: 1,
+            column: Some(type_end + 1),
+            message,
+        });
+    }
+}
+
+/// True when the message holds one of the prefixes directly followed by a number
+fn has_issue_reference(message: &str, prefixes: &[String]) -> bool {
+    prefixes.iter().filter(|p| !p.is_empty()).any(|prefix| {
+        message
+            .match_indices(prefix.as_str())
+            .any(|(at, _)| message[at + prefix.len()..].starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+
+    fn rules(message: &str, config: &LintConfig) -> Vec<LintRule> {
+        lint_commit_message(message, config)
+            .into_iter()
+            .map(|d| d.rule)
+            .collect()
+    }
+
+    #[test]
+    fn lints_conventional_commits() {
+        let config = LintConfig {
+            conventional: Some(ConventionalConfig {
+                scopes: vec!["ui".into(), "git".into()],
+                ..Default::default()
+            }),
+            issue_prefixes: vec!["#".into()],
+            ..Default::default()
+        };
+
+        assert!(rules("feat(ui)!: add dark mode\n\nCloses #12\n", &config).is_empty());
+        assert_eq!(rules("Add dark mode #12", &config), vec![LintRule::ConventionalFormat]);
+        assert_eq!(rules("feature(ui): x #12", &config), vec![LintRule::ConventionalType]);
+        assert_eq!(rules("fix(db): x #12", &config), vec![LintRule::ConventionalScope]);
+        assert_eq!(rules("fix: x\nbody #12", &config), vec![LintRule::BlankSecondLine]);
+        assert_eq!(rules("fix: x", &config), vec![LintRule::IssueReference]);
+        assert_eq!(rules("Merge branch 'topic' #1", &config), Vec::<LintRule>::new());
+
+        let long = format!(
+            "fix: {} #1\n\n{}\nhttps://example.com/{}",
+            "x".repeat(70),
+            "y".repeat(80),
+            "z".repeat(80)
+        );
+        let diagnostics = lint_commit_message(&long, &config);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            (diagnostics[0].rule, diagnostics[0].column),
+            (LintRule::SubjectLength, Some(73))
+        );
+        assert_eq!(
+            (diagnostics[1].rule, diagnostics[1].line),
+            (LintRule::BodyLineLength, 3)
+        );
+        assert_eq!(diagnostics[1].severity, LintSeverity::Warning);
+        assert_eq!(rules("  \n", &config), vec![LintRule::EmptyMessage]);
+    }
+
+    #[test]
+    fn loads_configured_and_repository_templates() {
+        let (dir, executor) = init_repo();
+        fs::write(dir.path().join(".gitmessage"), "# Subject\n\nfeat: \n").unwrap();
+        fs::create_dir(dir.path().join(".gitmessage.d")).unwrap();
+        fs::write(dir.path().join(".gitmessage.d/bugfix"), "fix: \n\nFixes #\n").unwrap();
+
+        let names = |templates: Vec<CommitTemplate>| -> Vec<(String, bool)> {
+            templates.into_iter().map(|t| (t.name, t.is_default)).collect()
+        };
+        assert_eq!(
+            names(get_commit_templates(&executor).unwrap()),
+            vec![(".gitmessage".to_string(), false), ("bugfix".to_string(), false)]
+        );
+
+        executor
+            .execute_checked(&["config", "commit.template", ".gitmessage"])
+            .unwrap();
+        let templates = get_commit_templates(&executor).unwrap();
+        assert_eq!(
+            names(templates.clone()),
+            vec![(".gitmessage".to_string(), true), ("bugfix".to_string(), false)]
+        );
+        assert_eq!(templates[0].message, "feat:");
+    }
+}
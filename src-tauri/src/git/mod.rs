@@ -4,9 +4,28 @@ pub mod executor;
 pub mod path;
 pub mod status;
 pub mod diff;
+pub mod blob_info;
+pub mod partial_patch;
+pub mod patch;
 pub mod commit;
+pub mod hooks;
+pub mod message;
 pub mod log;
+pub mod graph;
+pub mod commit_cache;
+pub mod search;
+pub mod signature;
+pub mod signing;
+pub mod stats;
+pub mod notes;
+pub mod blame;
+pub mod mailmap;
 pub mod branch;
+pub mod reflog;
 pub mod tag;
 pub mod remote;
 pub mod stash;
+pub mod submodule;
+
+#[cfg(test)]
+pub mod test_util;
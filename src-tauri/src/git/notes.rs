@@ -0,0 +1,147 @@
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::types::CommitNote;
+
+/// Notes refs shown by default: the one `git notes` writes to
+/// (core.notesRef, refs/notes/commits otherwise) and those matching notes.displayRef
+pub fn get_display_notes_refs(executor: &GitExecutor) -> Result<Vec<String>, GitError> {
+    let mut refs = vec![executor.execute_checked(&["notes", "get-ref"])?.trim().to_string()];
+
+    let patterns = executor.execute(&["config", "--get-all", "notes.displayRef"])?;
+    for pattern in patterns.stdout.lines().filter(|p| !p.is_empty()) {
+        let matching = executor.execute_checked(&["for-each-ref", "--format=%(refname)", pattern])?;
+        for notes_ref in matching.lines() {
+            if !refs.iter().any(|r| r == notes_ref) {
+                refs.push(notes_ref.to_string());
+            }
+        }
+    }
+
+    Ok(refs)
+}
+
+/// Get the notes attached to a commit under each of `notes_refs`, or under
+/// the default display refs when empty
+pub fn get_commit_notes(
+    executor: &GitExecutor,
+    hash: &str,
+    notes_refs: &[String],
+) -> Result<Vec<CommitNote>, GitError> {
+    let notes_refs = if notes_refs.is_empty() {
+        get_display_notes_refs(executor)?
+    } else {
+        notes_refs.to_vec()
+    };
+
+    let mut notes = Vec::new();
+    for notes_ref in notes_refs {
+        // `notes list <object>` exits with 1 when there is no note, and with
+        // 128 for other errors, whatever the language git speaks
+        let result = executor.execute(&["notes", "--ref", &notes_ref, "list", hash])?;
+        match result.exit_code {
+            0 => {
+                let blob = result.stdout.trim();
+                let message = executor.execute_checked(&["cat-file", "blob", blob])?;
+                notes.push(CommitNote {
+                    notes_ref: full_notes_ref(&notes_ref),
+                    message: message.trim_end().to_string(),
+                });
+            }
+            1 => {}
+            code => {
+                return Err(GitError::CommandFailed {
+                    code,
+                    stderr: result.stderr,
+                })
+            }
+        }
+    }
+
+    Ok(notes)
+}
+
+/// Attach a note to a commit, failing if it already has one under `notes_ref`
+pub fn add_note(executor: &GitExecutor, hash: &str, message: &str, notes_ref: Option<&str>) -> Result<(), GitError> {
+    write_note(executor, hash, message, notes_ref, false)
+}
+
+/// Replace the note of a commit, creating it if needed
+pub fn edit_note(executor: &GitExecutor, hash: &str, message: &str, notes_ref: Option<&str>) -> Result<(), GitError> {
+    write_note(executor, hash, message, notes_ref, true)
+}
+
+fn write_note(
+    executor: &GitExecutor,
+    hash: &str,
+    message: &str,
+    notes_ref: Option<&str>,
+    force: bool,
+) -> Result<(), GitError> {
+    let mut args = vec!["notes"];
+    if let Some(notes_ref) = notes_ref {
+        args.push("--ref");
+        args.push(notes_ref);
+    }
+    args.extend(["add", "--file", "-"]);
+    if force {
+        args.push("--force");
+    }
+    args.push(hash);
+
+    // The message goes through stdin so it is kept verbatim
+    executor.execute_with_stdin(&args, message)?;
+    Ok(())
+}
+
+pub fn remove_note(executor: &GitExecutor, hash: &str, notes_ref: Option<&str>) -> Result<(), GitError> {
+    let mut args = vec!["notes"];
+    if let Some(notes_ref) = notes_ref {
+        args.push("--ref");
+        args.push(notes_ref);
+    }
+    args.extend(["remove", hash]);
+
+    executor.execute_checked(&args)?;
+    Ok(())
+}
+
+/// `git notes --ref` accepts "review" for refs/notes/review
+fn full_notes_ref(notes_ref: &str) -> String {
+    if notes_ref.starts_with("refs/") {
+        notes_ref.to_string()
+    } else if let Some(rest) = notes_ref.strip_prefix("notes/") {
+        format!("refs/notes/{}", rest)
+    } else {
+        format!("refs/notes/{}", notes_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+
+    #[test]
+    fn notes_round_trip_under_several_refs() {
+        let (_dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        git(&["commit", "-q", "--allow-empty", "-m", "base"]);
+        let hash = git(&["rev-parse", "HEAD"]).trim().to_string();
+
+        add_note(&executor, &hash, "Tested on CI\n", None).unwrap();
+        assert!(add_note(&executor, &hash, "again", None).is_err());
+        add_note(&executor, &hash, "LGTM", Some("review")).unwrap();
+        git(&["config", "notes.displayRef", "refs/notes/review"]);
+
+        let notes = get_commit_notes(&executor, &hash, &[]).unwrap();
+        let summary: Vec<(&str, &str)> = notes.iter().map(|n| (n.notes_ref.as_str(), n.message.as_str())).collect();
+        assert_eq!(summary, vec![("refs/notes/commits", "Tested on CI"), ("refs/notes/review", "LGTM")]);
+
+        edit_note(&executor, &hash, "Ship it", Some("review")).unwrap();
+        remove_note(&executor, &hash, None).unwrap();
+        let notes = get_commit_notes(&executor, &hash, &[]).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].message, "Ship it");
+        assert!(get_commit_notes(&executor, "0123456", &[]).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+
+use crate::git::error::GitError;
+use crate::git::types::*;
+
+/// A patch built from a selection of lines of a `FileDiff`
+#[derive(Debug, Clone)]
+pub struct PartialPatch {
+    pub text: String,
+    /// True if some hunk has no context lines, which `git apply` only accepts with `--unidiff-zero`
+    pub zero_context: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PatchLineKind {
+    Context,
+    Addition,
+    Deletion,
+}
+
+#[derive(Debug, Clone)]
+struct PatchLine<'a> {
+    kind: PatchLineKind,
+    content: &'a str,
+    crlf: bool,
+    no_newline: bool,
+}
+
+/// Generate a patch containing only the selected lines of `diff`.
+///
+/// The patch always targets `path` on both sides: line selections never carry
+/// renames or mode changes, those are staged together with the whole file.
+///
+/// When `reverse` is false the patch is meant to be applied forward on top of
+/// the old side of the diff (staging): unselected deletions are kept as context
+/// and unselected additions are dropped. When `reverse` is true it is meant to
+/// be applied with `--reverse` on top of the new side (unstaging, discarding):
+/// unselected additions are kept as context and unselected deletions are dropped.
+pub fn generate_partial_patch(
+    path: &str,
+    diff: &FileDiff,
+    line_indices_by_hunk: &HashMap<usize, Vec<usize>>,
+    reverse: bool,
+) -> Result<Option<PartialPatch>, GitError> {
+    let mut body = String::new();
+    let mut zero_context = false;
+    // Lines added minus lines removed by the hunks emitted so far
+    let mut delta: i64 = 0;
+
+    for (hunk_idx, hunk) in diff.hunks.iter().enumerate() {
+        let selected_indices = match line_indices_by_hunk.get(&hunk_idx) {
+            Some(indices) => indices,
+            None => continue,
+        };
+
+        let lines = select_hunk_lines(hunk, selected_indices, reverse);
+        if !lines.iter().any(|l| l.kind != PatchLineKind::Context) {
+            continue;
+        }
+
+        let lines = fix_missing_newlines(lines);
+
+        let old_count = lines.iter().filter(|l| l.kind != PatchLineKind::Addition).count() as i64;
+        let new_count = lines.iter().filter(|l| l.kind != PatchLineKind::Deletion).count() as i64;
+
+        // The side the patch is applied to keeps the original positions, the
+        // other side is shifted by what the previous partial hunks changed
+        let (old_start, new_start) = if reverse {
+            let new_start = hunk.new_start as i64;
+            let new_first = first_line(new_start, new_count);
+            (range_start(new_first - delta, old_count), new_start)
+        } else {
+            let old_start = hunk.old_start as i64;
+            let old_first = first_line(old_start, old_count);
+            (old_start, range_start(old_first + delta, new_count))
+        };
+        delta += new_count - old_count;
+
+        if lines.iter().all(|l| l.kind != PatchLineKind::Context) {
+            zero_context = true;
+        }
+
+        body.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_count, new_start, new_count
+        ));
+        for line in &lines {
+            body.push(match line.kind {
+                PatchLineKind::Context => ' ',
+                PatchLineKind::Addition => '+',
+                PatchLineKind::Deletion => '-',
+            });
+            body.push_str(line.content);
+            if line.crlf {
+                body.push('\r');
+            }
+            body.push('\n');
+            if line.no_newline {
+                body.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    if body.is_empty() {
+        return Ok(None);
+    }
+
+    let mut text = String::new();
+    text.push_str(&format!("diff --git {} {}\n", prefixed("a/", path), prefixed("b/", path)));
+    text.push_str(&format!("--- {}\n", prefixed("a/", path)));
+    text.push_str(&format!("+++ {}\n", prefixed("b/", path)));
+    text.push_str(&body);
+
+    Ok(Some(PartialPatch { text, zero_context }))
+}
+
+/// Turn a hunk into patch lines according to the selection
+fn select_hunk_lines<'a>(hunk: &'a DiffHunk, selected_indices: &[usize], reverse: bool) -> Vec<PatchLine<'a>> {
+    let mut lines = Vec::with_capacity(hunk.lines.len());
+
+    for (line_idx, line) in hunk.lines.iter().enumerate() {
+        let is_selected = selected_indices.contains(&line_idx);
+
+        let kind = match (&line.line_type, is_selected, reverse) {
+            (DiffLineType::Context, _, _) => PatchLineKind::Context,
+            (DiffLineType::Addition, true, _) => PatchLineKind::Addition,
+            (DiffLineType::Deletion, true, _) => PatchLineKind::Deletion,
+            // The line exists in the target file, keep it as context
+            (DiffLineType::Addition, false, true) | (DiffLineType::Deletion, false, false) => {
+                PatchLineKind::Context
+            }
+            // The line does not exist in the target file, leave it out
+            (DiffLineType::Addition, false, false) | (DiffLineType::Deletion, false, true) => continue,
+            (DiffLineType::Header, _, _) => continue,
+        };
+
+        lines.push(PatchLine {
+            kind,
+            content: &line.content,
+            crlf: line.crlf,
+            no_newline: line.no_newline,
+        });
+    }
+
+    lines
+}
+
+/// A line without a trailing newline must be the last line of its side.
+///
+/// Dropping or converting lines can leave such a line followed by other lines,
+/// e.g. an old last line kept as context followed by selected additions. The
+/// side being built gets the newline, the side matching the target keeps the
+/// marker so the patch still applies.
+fn fix_missing_newlines(lines: Vec<PatchLine<'_>>) -> Vec<PatchLine<'_>> {
+    let mut fixed = Vec::with_capacity(lines.len() + 1);
+
+    for (i, line) in lines.iter().enumerate() {
+        if !line.no_newline {
+            fixed.push(line.clone());
+            continue;
+        }
+
+        let rest = &lines[i + 1..];
+        let old_continues = rest.iter().any(|l| l.kind != PatchLineKind::Addition);
+        let new_continues = rest.iter().any(|l| l.kind != PatchLineKind::Deletion);
+
+        match line.kind {
+            PatchLineKind::Context if old_continues || new_continues => {
+                fixed.push(PatchLine {
+                    kind: PatchLineKind::Deletion,
+                    no_newline: !old_continues,
+                    ..line.clone()
+                });
+                fixed.push(PatchLine {
+                    kind: PatchLineKind::Addition,
+                    no_newline: !new_continues,
+                    ..line.clone()
+                });
+            }
+            PatchLineKind::Deletion if old_continues => {
+                fixed.push(PatchLine { no_newline: false, ..line.clone() });
+            }
+            PatchLineKind::Addition if new_continues => {
+                fixed.push(PatchLine { no_newline: false, ..line.clone() });
+            }
+            _ => fixed.push(line.clone()),
+        }
+    }
+
+    fixed
+}
+
+/// First line covered by a range given its start and count
+fn first_line(start: i64, count: i64) -> i64 {
+    if count == 0 {
+        start + 1
+    } else {
+        start
+    }
+}
+
+/// Range start for a hunk header: empty ranges point at the line before
+fn range_start(first: i64, count: i64) -> i64 {
+    if count == 0 {
+        (first - 1).max(0)
+    } else {
+        first
+    }
+}
+
+/// Quote a path the way git does when it contains special characters
+fn quote_path(path: &str) -> Option<String> {
+    if !path.chars().any(|c| matches!(c, '"' | '\\' | '\t' | '\n' | '\r')) {
+        return None;
+    }
+
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Some(quoted)
+}
+
+/// Prefix a path for a patch header, quoting it if needed
+fn prefixed(prefix: &str, path: &str) -> String {
+    let full = format!("{}{}", prefix, path);
+    quote_path(&full).unwrap_or(full)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::{discard_lines, get_file_diff, stage_lines, unstage_lines};
+    use crate::git::executor::GitExecutor;
+    use crate::git::test_util;
+    use proptest::prelude::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A generated line: content and whether it ends with CRLF
+    type Line = (&'static str, bool);
+
+    fn file_text(lines: &[Line], final_newline: bool) -> String {
+        let mut text = String::new();
+        for (i, (content, crlf)) in lines.iter().enumerate() {
+            text.push_str(content);
+            if *crlf {
+                text.push('\r');
+            }
+            if final_newline || i + 1 < lines.len() {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    fn init_repo() -> (TempDir, GitExecutor) {
+        let (dir, executor) = test_util::init_repo();
+        executor.execute_checked(&["config", "core.autocrlf", "false"]).unwrap();
+        executor.execute_checked(&["config", "core.filemode", "true"]).unwrap();
+        (dir, executor)
+    }
+
+    fn write(dir: &TempDir, path: &str, content: &str) {
+        fs::write(dir.path().join(path), content).unwrap();
+    }
+
+    fn read(dir: &TempDir, path: &str) -> String {
+        fs::read_to_string(dir.path().join(path)).unwrap()
+    }
+
+    fn index_content(executor: &GitExecutor, path: &str) -> String {
+        let bytes = executor.execute_raw(&["show", &format!(":{}", path)]).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn commit_file(dir: &TempDir, executor: &GitExecutor, path: &str, content: &str) {
+        write(dir, path, content);
+        executor.execute_checked(&["add", "--", path]).unwrap();
+        executor.execute_checked(&["commit", "-q", "-m", "base"]).unwrap();
+    }
+
+    /// Pick lines to select from the added and deleted lines of every hunk
+    fn selection_from(diff: &FileDiff, flags: &[bool]) -> HashMap<usize, Vec<usize>> {
+        let mut selection = HashMap::new();
+        let mut n = 0;
+        for (hunk_idx, hunk) in diff.hunks.iter().enumerate() {
+            let mut selected = Vec::new();
+            for (line_idx, line) in hunk.lines.iter().enumerate() {
+                if line.line_type != DiffLineType::Context {
+                    if flags[n % flags.len()] {
+                        selected.push(line_idx);
+                    }
+                    n += 1;
+                }
+            }
+            selection.insert(hunk_idx, selected);
+        }
+        selection
+    }
+
+    /// Expected content after applying the selection on top of `base`, which is
+    /// the old side of the diff when staging and the new side otherwise
+    fn expected_result(
+        base: &str,
+        diff: &FileDiff,
+        selection: &HashMap<usize, Vec<usize>>,
+        reverse: bool,
+    ) -> String {
+        let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+        let mut out: Vec<(String, bool)> = Vec::new();
+        let push_base = |out: &mut Vec<(String, bool)>, line: &str| {
+            match line.strip_suffix('\n') {
+                Some(content) => out.push((content.to_string(), true)),
+                None => out.push((line.to_string(), false)),
+            }
+        };
+        let mut pos = 0usize;
+
+        for (hunk_idx, hunk) in diff.hunks.iter().enumerate() {
+            let (start, count) = if reverse {
+                (hunk.new_start, hunk.new_lines)
+            } else {
+                (hunk.old_start, hunk.old_lines)
+            };
+            let first = if count == 0 { start } else { start - 1 } as usize;
+            while pos < first {
+                push_base(&mut out, base_lines[pos]);
+                pos += 1;
+            }
+
+            let selected = selection.get(&hunk_idx).cloned().unwrap_or_default();
+            for (line_idx, line) in hunk.lines.iter().enumerate() {
+                let is_selected = selected.contains(&line_idx);
+                let in_base = match line.line_type {
+                    DiffLineType::Addition => reverse,
+                    DiffLineType::Deletion => !reverse,
+                    _ => true,
+                };
+                if in_base {
+                    if line.line_type == DiffLineType::Context || !is_selected {
+                        push_base(&mut out, base_lines[pos]);
+                    }
+                    pos += 1;
+                } else if is_selected {
+                    let mut content = line.content.clone();
+                    if line.crlf {
+                        content.push('\r');
+                    }
+                    out.push((content, !line.no_newline));
+                }
+            }
+        }
+        while pos < base_lines.len() {
+            push_base(&mut out, base_lines[pos]);
+            pos += 1;
+        }
+
+        let mut text = String::new();
+        let len = out.len();
+        for (i, (content, newline)) in out.into_iter().enumerate() {
+            text.push_str(&content);
+            if newline || i + 1 < len {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    fn lines_strategy() -> impl Strategy<Value = Vec<Line>> {
+        prop::collection::vec((prop::sample::select(vec!["a", "b", "c", "d"]), any::<bool>()), 0..10)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        #[test]
+        fn staged_lines_match_selection(
+            old in lines_strategy(),
+            old_newline in any::<bool>(),
+            new in lines_strategy(),
+            new_newline in any::<bool>(),
+            flags in prop::collection::vec(any::<bool>(), 1..32),
+        ) {
+            let (dir, executor) = init_repo();
+            let old_text = file_text(&old, old_newline);
+            let new_text = file_text(&new, new_newline);
+            commit_file(&dir, &executor, "f.txt", &old_text);
+            write(&dir, "f.txt", &new_text);
+
+            let diff = get_file_diff(&executor, "f.txt", false, false).unwrap();
+            let selection = selection_from(&diff, &flags);
+            stage_lines(&executor, "f.txt", selection.clone(), Some(&diff.fingerprint)).unwrap();
+
+            prop_assert_eq!(index_content(&executor, "f.txt"), expected_result(&old_text, &diff, &selection, false));
+            prop_assert_eq!(read(&dir, "f.txt"), new_text);
+        }
+
+        #[test]
+        fn unstaged_lines_match_selection(
+            old in lines_strategy(),
+            old_newline in any::<bool>(),
+            new in lines_strategy(),
+            new_newline in any::<bool>(),
+            flags in prop::collection::vec(any::<bool>(), 1..32),
+        ) {
+            let (dir, executor) = init_repo();
+            let old_text = file_text(&old, old_newline);
+            let new_text = file_text(&new, new_newline);
+            commit_file(&dir, &executor, "f.txt", &old_text);
+            write(&dir, "f.txt", &new_text);
+            executor.execute_checked(&["add", "--", "f.txt"]).unwrap();
+
+            let diff = get_file_diff(&executor, "f.txt", true, false).unwrap();
+            let selection = selection_from(&diff, &flags);
+            unstage_lines(&executor, "f.txt", selection.clone(), Some(&diff.fingerprint)).unwrap();
+
+            prop_assert_eq!(index_content(&executor, "f.txt"), expected_result(&new_text, &diff, &selection, true));
+            prop_assert_eq!(read(&dir, "f.txt"), new_text);
+        }
+
+        #[test]
+        fn discarded_lines_match_selection(
+            old in lines_strategy(),
+            old_newline in any::<bool>(),
+            new in lines_strategy(),
+            new_newline in any::<bool>(),
+            flags in prop::collection::vec(any::<bool>(), 1..32),
+        ) {
+            let (dir, executor) = init_repo();
+            let old_text = file_text(&old, old_newline);
+            let new_text = file_text(&new, new_newline);
+            commit_file(&dir, &executor, "f.txt", &old_text);
+            write(&dir, "f.txt", &new_text);
+
+            let diff = get_file_diff(&executor, "f.txt", false, false).unwrap();
+            let selection = selection_from(&diff, &flags);
+            discard_lines(&executor, "f.txt", selection.clone(), Some(&diff.fingerprint)).unwrap();
+
+            prop_assert_eq!(read(&dir, "f.txt"), expected_result(&new_text, &diff, &selection, true));
+            prop_assert_eq!(index_content(&executor, "f.txt"), old_text);
+        }
+    }
+
+    #[test]
+    fn unstage_lines_keeps_rename() {
+        let (dir, executor) = init_repo();
+        commit_file(&dir, &executor, "old.txt", "a\nb\nc\n");
+        executor.execute_checked(&["mv", "old.txt", "new.txt"]).unwrap();
+        write(&dir, "new.txt", "a\nB\nc\nd\n");
+        executor.execute_checked(&["add", "--", "new.txt"]).unwrap();
+
+        let diff = get_file_diff(&executor, "new.txt", true, false).unwrap();
+        let all_but_last: HashMap<usize, Vec<usize>> = diff
+            .hunks
+            .iter()
+            .enumerate()
+            .map(|(i, hunk)| (i, (0..hunk.lines.len().saturating_sub(1)).collect()))
+            .collect();
+        unstage_lines(&executor, "new.txt", all_but_last, None).unwrap();
+
+        assert_eq!(index_content(&executor, "new.txt"), "d\n");
+        let staged = executor.execute_checked(&["diff", "--cached", "--name-status"]).unwrap();
+        assert!(staged.contains("D\told.txt"), "{}", staged);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stage_lines_ignores_mode_change() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (dir, executor) = init_repo();
+        commit_file(&dir, &executor, "run.sh", "one\ntwo\n");
+        write(&dir, "run.sh", "one\n2\ntwo\nthree\n");
+        fs::set_permissions(dir.path().join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let diff = get_file_diff(&executor, "run.sh", false, false).unwrap();
+        let first_addition: HashMap<usize, Vec<usize>> = [(0, vec![1])].into_iter().collect();
+        assert_eq!(diff.hunks[0].lines[1].line_type, DiffLineType::Addition);
+        stage_lines(&executor, "run.sh", first_addition, Some(&diff.fingerprint)).unwrap();
+
+        assert_eq!(index_content(&executor, "run.sh"), "one\n2\ntwo\n");
+        let stage = executor.execute_checked(&["ls-files", "-s", "--", "run.sh"]).unwrap();
+        assert!(stage.starts_with("100644"), "{}", stage);
+    }
+
+    #[test]
+    fn stale_fingerprint_is_rejected() {
+        let (dir, executor) = init_repo();
+        commit_file(&dir, &executor, "f.txt", "a\n");
+        write(&dir, "f.txt", "a\nb\n");
+
+        let result = stage_lines(&executor, "f.txt", [(0, vec![1])].into_iter().collect(), Some("stale"));
+        assert!(matches!(result, Err(GitError::DiffChanged { .. })));
+    }
+
+    #[test]
+    fn quotes_special_paths() {
+        assert_eq!(prefixed("a/", "plain name.txt"), "a/plain name.txt");
+        assert_eq!(prefixed("a/", "tab\there"), "\"a/tab\\there\"");
+    }
+}
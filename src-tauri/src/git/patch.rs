@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::diff::parse_multi_diff;
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+
+/// Output format of an exported patch
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatchFormat {
+    /// Plain unified diff (`git diff --binary`)
+    Diff,
+    /// Mailbox series with one message per commit (`git format-patch`)
+    Mbox,
+}
+
+/// Result of a dry run of `git apply`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchCheck {
+    /// True if the patch applies cleanly as-is
+    pub applies: bool,
+    /// Whether a three-way merge would succeed, only checked when requested
+    pub applies_with_three_way: Option<bool>,
+    pub files: Vec<PatchFileCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFileCheck {
+    /// Index of the message in a mailbox series, 0 for a plain diff
+    pub patch: usize,
+    pub path: String,
+    pub applies: bool,
+    /// Indexes of the hunks of this file that do not apply
+    pub failed_hunks: Vec<usize>,
+    pub errors: Vec<String>,
+}
+
+/// Export the working tree (or staged) changes as a unified diff
+pub fn export_working_diff(
+    executor: &GitExecutor,
+    output_path: &str,
+    staged: bool,
+    paths: &[String],
+) -> Result<(), GitError> {
+    let mut args = vec!["diff", "--binary"];
+    if staged {
+        args.push("--cached");
+    }
+    if !paths.is_empty() {
+        args.push("--");
+        args.extend(paths.iter().map(|p| p.as_str()));
+    }
+
+    let bytes = executor.execute_raw(&args)?;
+    write_patch(output_path, &bytes)
+}
+
+/// Export a commit, or the commits in `from..to`, as a diff or mailbox series
+pub fn export_commits(
+    executor: &GitExecutor,
+    from: Option<&str>,
+    to: &str,
+    format: PatchFormat,
+    output_path: &str,
+) -> Result<(), GitError> {
+    // Never let a revision be read as an option
+    if let Some(option) = [from, Some(to)].into_iter().flatten().find(|r| r.starts_with('-')) {
+        return Err(GitError::RevisionNotFound {
+            revision: option.to_string(),
+        });
+    }
+
+    let bytes = match (format, from) {
+        (PatchFormat::Mbox, Some(from)) => executor.execute_raw(&[
+            "format-patch",
+            "--stdout",
+            "--binary",
+            &format!("{}..{}", from, to),
+        ])?,
+        (PatchFormat::Mbox, None) => {
+            executor.execute_raw(&["format-patch", "--stdout", "--binary", "-1", to])?
+        }
+        (PatchFormat::Diff, Some(from)) => executor.execute_raw(&["diff", "--binary", from, to])?,
+        // Same as get_commit_diff: show merges against their first parent
+        (PatchFormat::Diff, None) => {
+            executor.execute_raw(&["show", "--format=", "--binary", "--first-parent", to])?
+        }
+    };
+
+    write_patch(output_path, &bytes)
+}
+
+fn write_patch(output_path: &str, bytes: &[u8]) -> Result<(), GitError> {
+    fs::write(output_path, bytes).map_err(|e| GitError::IoError {
+        message: format!("Failed to write patch {}: {}", output_path, e),
+    })
+}
+
+/// Read a patch file; relative paths are taken from the repository root,
+/// like git does when it is given the same path
+fn read_patch(executor: &GitExecutor, patch_path: &str) -> Result<Vec<u8>, GitError> {
+    fs::read(Path::new(executor.repo_path()).join(patch_path)).map_err(|e| GitError::IoError {
+        message: format!("Failed to read patch {}: {}", patch_path, e),
+    })
+}
+
+/// Check whether a file is a mailbox produced by `git format-patch`
+fn is_mbox(content: &[u8]) -> bool {
+    let first_line = content.split(|&b| b == b'\n').next().unwrap_or_default();
+    first_line.starts_with(b"From ") && content.windows(9).any(|w| w == b"\nSubject:")
+}
+
+/// Keep only the diffs of a mailbox, one string per message: the mail
+/// headers, commit messages, "---" separators, diffstats and signatures
+/// around them are dropped
+fn mbox_diffs(content: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut in_diff = false;
+
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("From ") {
+            messages.push(String::new());
+        }
+        if line.starts_with("diff --git ") {
+            in_diff = true;
+        } else if line.trim_end_matches('\n') == "-- " || line.starts_with("From ") {
+            // The signature that closes a message, or the next message
+            in_diff = false;
+        }
+        if let Some(diffs) = messages.last_mut().filter(|_| in_diff) {
+            diffs.push_str(line);
+        }
+    }
+
+    messages
+}
+
+/// Whether the index has unmerged entries left by a three-way apply
+fn has_conflicts(executor: &GitExecutor) -> Result<bool, GitError> {
+    Ok(!executor.execute_checked(&["ls-files", "--unmerged"])?.is_empty())
+}
+
+/// Apply a patch file to the working tree, or a mailbox series as commits
+pub fn apply_patch(executor: &GitExecutor, patch_path: &str, three_way: bool) -> Result<(), GitError> {
+    let mut args = if is_mbox(&read_patch(executor, patch_path)?) {
+        vec!["am"]
+    } else {
+        vec!["apply"]
+    };
+    if three_way {
+        args.push("--3way");
+    }
+    args.push(patch_path);
+
+    let result = executor.execute(&args)?;
+
+    if result.exit_code != 0 {
+        if three_way && has_conflicts(executor)? {
+            return Err(GitError::MergeConflict);
+        }
+        return Err(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        });
+    }
+
+    Ok(())
+}
+
+/// Dry run a patch or mailbox against the working tree and report which
+/// files and hunks would fail
+pub fn check_patch(executor: &GitExecutor, patch_path: &str, three_way: bool) -> Result<PatchCheck, GitError> {
+    let patch = read_patch(executor, patch_path)?;
+    let text = String::from_utf8_lossy(&patch);
+    let messages = if is_mbox(&patch) {
+        mbox_diffs(&text)
+    } else {
+        vec![text.into_owned()]
+    };
+    let mut file_diffs = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        file_diffs.extend(parse_multi_diff(message)?.into_iter().map(|d| (index, d)));
+    }
+
+    // --reject makes git go through every hunk instead of stopping at the first failure
+    let result = executor.execute(&["apply", "--check", "--verbose", "--reject", patch_path])?;
+    let mut files = parse_apply_check(&result.stderr);
+
+    // git checks the file diffs in order, the same file may come up again
+    // in a later message of a series
+    let mut remaining = file_diffs.iter();
+    for file in &mut files {
+        let diff = remaining
+            .by_ref()
+            .find(|(_, d)| d.new_path == file.path || d.old_path.as_deref() == Some(file.path.as_str()));
+        let hunk_starts: Vec<u32> = diff.iter().flat_map(|(_, d)| d.hunks.iter().map(|h| h.old_start)).collect();
+        file.patch = diff.map(|(index, _)| *index).unwrap_or_default();
+        file.failed_hunks = file
+            .errors
+            .iter()
+            .filter_map(|e| e.strip_prefix("patch failed: "))
+            .filter_map(|e| e.rsplit(':').next()?.parse::<u32>().ok())
+            .filter_map(|line| hunk_starts.iter().position(|&start| start == line))
+            .collect();
+        file.applies = file.errors.is_empty();
+    }
+
+    let applies = result.exit_code == 0 && files.iter().all(|f| f.applies);
+
+    let applies_with_three_way = if three_way && !applies {
+        Some(check_three_way(executor, patch_path)?)
+    } else if three_way {
+        Some(true)
+    } else {
+        None
+    };
+
+    Ok(PatchCheck {
+        applies,
+        applies_with_three_way,
+        files,
+    })
+}
+
+/// Whether `git am -3` or `git apply --3way` would get through the patch
+/// without conflicts. `apply --check --3way` succeeds even when the merge
+/// conflicts, so the patch is really applied, with the same fallback to a
+/// three-way merge of the blobs named in its index lines, but to a copy of
+/// the index. Like `am`, this works from the index rather than the working
+/// tree; the patches of a mailbox are applied one after the other.
+fn check_three_way(executor: &GitExecutor, patch_path: &str) -> Result<bool, GitError> {
+    let git_path = |name: &str| -> Result<PathBuf, GitError> {
+        let path = executor.execute_checked(&["rev-parse", "--git-path", name])?;
+        Ok(Path::new(executor.repo_path()).join(path.trim()))
+    };
+    let index = git_path("index")?;
+    let scratch = git_path("grist-patch-check.index")?;
+    let io_error = |e: std::io::Error| GitError::IoError {
+        message: format!("Failed to prepare the patch check: {}", e),
+    };
+    match fs::copy(&index, &scratch) {
+        Ok(_) => {}
+        // A repository without commits has no index yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => fs::write(&scratch, b"").map_err(io_error)?,
+        Err(e) => return Err(io_error(e)),
+    }
+
+    let env = HashMap::from([("GIT_INDEX_FILE".to_string(), scratch.to_string_lossy().to_string())]);
+    let result = executor.execute_with_env(&["apply", "--cached", "--3way", patch_path], &env);
+    let _ = fs::remove_file(&scratch);
+    Ok(result?.exit_code == 0)
+}
+
+/// Parse the verbose output of `git apply --check`
+fn parse_apply_check(output: &str) -> Vec<PatchFileCheck> {
+    let mut files: Vec<PatchFileCheck> = Vec::new();
+
+    for line in output.lines() {
+        if let Some(path) = line
+            .strip_prefix("Checking patch ")
+            .and_then(|rest| rest.strip_suffix("..."))
+        {
+            // A rename is reported as "old => new"
+            let path = path.rsplit(" => ").next().unwrap_or(path);
+            files.push(PatchFileCheck {
+                patch: 0,
+                path: path.to_string(),
+                applies: true,
+                failed_hunks: Vec::new(),
+                errors: Vec::new(),
+            });
+        } else if let Some(message) = line.strip_prefix("error: ") {
+            // The context dump after "while searching for:" is not useful here
+            if message == "while searching for:" {
+                continue;
+            }
+            if let Some(file) = files.last_mut() {
+                file.errors.push(message.to_string());
+            }
+        }
+    }
+
+    files
+}
+
+pub fn abort_am(executor: &GitExecutor) -> Result<(), GitError> {
+    executor.execute_checked(&["am", "--abort"])?;
+    Ok(())
+}
+
+pub fn continue_am(executor: &GitExecutor) -> Result<(), GitError> {
+    executor.execute_checked(&["am", "--continue"])?;
+    Ok(())
+}
+
+pub fn skip_am(executor: &GitExecutor) -> Result<(), GitError> {
+    executor.execute_checked(&["am", "--skip"])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use tempfile::TempDir;
+
+    fn commit_file(dir: &TempDir, executor: &GitExecutor, content: &str, message: &str) {
+        fs::write(dir.path().join("a.txt"), content).unwrap();
+        executor.execute_checked(&["add", "a.txt"]).unwrap();
+        executor.execute_checked(&["commit", "-q", "-m", message]).unwrap();
+    }
+
+    fn numbered(replace: &[(usize, &str)]) -> String {
+        (1..=15)
+            .map(|i| {
+                let line = replace.iter().find(|(n, _)| *n == i).map(|(_, s)| s.to_string());
+                line.unwrap_or_else(|| i.to_string()) + "\n"
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_reports_failing_hunk() {
+        let (dir, executor) = init_repo();
+        let patch_path = dir.path().join("changes.diff");
+        let patch_path = patch_path.to_str().unwrap();
+        commit_file(&dir, &executor, &numbered(&[]), "base");
+
+        fs::write(dir.path().join("a.txt"), numbered(&[(2, "two"), (14, "fourteen")])).unwrap();
+        export_working_diff(&executor, patch_path, false, &[]).unwrap();
+        executor.execute_checked(&["checkout", "--", "a.txt"]).unwrap();
+        fs::write(dir.path().join("a.txt"), numbered(&[(2, "TWO")])).unwrap();
+
+        let check = check_patch(&executor, patch_path, false).unwrap();
+        assert!(!check.applies);
+        assert_eq!(check.files.len(), 1);
+        assert_eq!(check.files[0].path, "a.txt");
+        assert_eq!(check.files[0].failed_hunks, vec![0]);
+        assert_eq!(check.applies_with_three_way, None);
+    }
+
+    #[test]
+    fn mbox_series_round_trip() {
+        let (dir, executor) = init_repo();
+        let mbox_path = dir.path().join("series.mbox");
+        let mbox_path = mbox_path.to_str().unwrap();
+        commit_file(&dir, &executor, &numbered(&[]), "base");
+        let base = executor.execute_checked(&["rev-parse", "HEAD"]).unwrap();
+        let base = base.trim();
+        commit_file(&dir, &executor, &numbered(&[(1, "one")]), "first");
+        commit_file(&dir, &executor, &numbered(&[(1, "one"), (15, "fifteen")]), "second");
+
+        export_commits(&executor, Some(base), "HEAD", PatchFormat::Mbox, mbox_path).unwrap();
+        executor.execute_checked(&["reset", "-q", "--hard", base]).unwrap();
+
+        assert!(check_patch(&executor, mbox_path, false).unwrap().applies);
+        apply_patch(&executor, mbox_path, false).unwrap();
+
+        let subjects = executor.execute_checked(&["log", "--format=%s", "-3"]).unwrap();
+        assert_eq!(subjects, "second\nfirst\nbase\n");
+    }
+
+    #[test]
+    fn check_maps_failures_to_their_message() {
+        let (dir, executor) = init_repo();
+        commit_file(&dir, &executor, &numbered(&[]), "base");
+        let base = executor.execute_checked(&["rev-parse", "HEAD"]).unwrap();
+        let base = base.trim();
+        commit_file(&dir, &executor, &numbered(&[(2, "two")]), "first");
+        commit_file(&dir, &executor, &numbered(&[(2, "two"), (14, "fourteen")]), "second");
+        let mbox_path = dir.path().join("series.mbox");
+        export_commits(&executor, Some(base), "HEAD", PatchFormat::Mbox, mbox_path.to_str().unwrap()).unwrap();
+
+        executor.execute_checked(&["reset", "-q", "--hard", base]).unwrap();
+        commit_file(&dir, &executor, &numbered(&[(14, "FOURTEEN")]), "conflicting");
+
+        // A relative path is taken from the repository, not the process
+        let check = check_patch(&executor, "series.mbox", false).unwrap();
+        assert!(!check.applies);
+        let files: Vec<_> = check.files.iter().map(|f| (f.patch, f.path.as_str(), f.failed_hunks.clone())).collect();
+        assert_eq!(files, vec![(0, "a.txt", vec![]), (1, "a.txt", vec![0])]);
+    }
+
+    #[test]
+    fn three_way_check_follows_am() {
+        let (dir, executor) = init_repo();
+        let mbox_path = dir.path().join("change.mbox");
+        let mbox_path = mbox_path.to_str().unwrap();
+        commit_file(&dir, &executor, &numbered(&[]), "base");
+        let base = executor.execute_checked(&["rev-parse", "HEAD"]).unwrap();
+        let base = base.trim();
+        commit_file(&dir, &executor, &numbered(&[(2, "two"), (14, "fourteen")]), "change");
+        export_commits(&executor, None, "HEAD", PatchFormat::Mbox, mbox_path).unwrap();
+        let diffs = parse_multi_diff(&mbox_diffs(&fs::read_to_string(mbox_path).unwrap())[0]).unwrap();
+        let last_line = diffs[0].hunks.last().and_then(|h| h.lines.last()).unwrap();
+        assert_eq!((diffs[0].hunks.len(), last_line.content.as_str()), (2, "15"));
+
+        // The context of the first hunk changed, but the merge is clean
+        executor.execute_checked(&["reset", "-q", "--hard", base]).unwrap();
+        commit_file(&dir, &executor, &numbered(&[(4, "FOUR")]), "nearby");
+        let check = check_patch(&executor, mbox_path, true).unwrap();
+        assert!(!check.applies);
+        assert_eq!(check.files[0].failed_hunks, vec![0]);
+        assert_eq!(check.applies_with_three_way, Some(true));
+        assert!(executor.execute_checked(&["status", "--porcelain", "-uno"]).unwrap().is_empty());
+        apply_patch(&executor, mbox_path, true).unwrap();
+
+        // The same line changed: the three-way merge conflicts
+        executor.execute_checked(&["reset", "-q", "--hard", base]).unwrap();
+        commit_file(&dir, &executor, &numbered(&[(2, "TWO")]), "same line");
+        assert_eq!(check_patch(&executor, mbox_path, true).unwrap().applies_with_three_way, Some(false));
+        assert!(matches!(apply_patch(&executor, mbox_path, true), Err(GitError::MergeConflict)));
+        abort_am(&executor).unwrap();
+    }
+}
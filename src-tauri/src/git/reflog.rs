@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflogEntry {
+    /// Selector of the entry, e.g. "HEAD@{2}"
+    pub selector: String,
+    /// Commit the ref pointed to before and after the update, None for the
+    /// oldest entry, which usually created the ref
+    pub old_oid: Option<String>,
+    pub new_oid: String,
+    /// What updated the ref: "commit", "commit (amend)", "checkout", "reset", ...
+    pub action: String,
+    pub message: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub timestamp: i64,
+}
+
+/// Get the reflog of HEAD or of a branch, newest entry first
+pub fn get_reflog(
+    executor: &GitExecutor,
+    reference: Option<&str>,
+    count: u32,
+    skip: u32,
+) -> Result<Vec<ReflogEntry>, GitError> {
+    let reference = reference.unwrap_or("HEAD");
+    // Never let the reference be read as an option
+    if reference.starts_with('-') {
+        return Err(GitError::RevisionNotFound {
+            revision: reference.to_string(),
+        });
+    }
+    let full_ref = if reference == "HEAD" || reference.starts_with("refs/") {
+        reference.to_string()
+    } else {
+        let resolved = executor.execute_checked(&["rev-parse", "--symbolic-full-name", reference])?;
+        match resolved.trim() {
+            // A range or other expression resolves to several names
+            name if name.is_empty() || name.contains('\n') => {
+                return Err(GitError::BranchNotFound {
+                    name: reference.to_string(),
+                })
+            }
+            name => name.to_string(),
+        }
+    };
+
+    // One entry more than asked for: the old id of an entry is the new id of
+    // the entry before it, which `log -g` does not print itself
+    let count_arg = format!("--max-count={}", count as u64 + 1);
+    let skip_arg = format!("--skip={}", skip);
+    let output = executor.execute_checked(&[
+        "log",
+        "--walk-reflogs",
+        "-z",
+        "--date=unix",
+        "--format=%gd%x00%H%x00%gn%x00%ge%x00%gs",
+        &count_arg,
+        &skip_arg,
+        &full_ref,
+        "--",
+    ])?;
+
+    let mut entries = parse_reflog(&output, reference, skip);
+    let older: Vec<String> = entries.iter().skip(1).map(|e| e.new_oid.clone()).collect();
+    for (entry, old_oid) in entries.iter_mut().zip(older) {
+        entry.old_oid = Some(old_oid);
+    }
+    entries.truncate(count as usize);
+    Ok(entries)
+}
+
+/// Parse `log --walk-reflogs -z --date=unix` output, five NUL separated
+/// fields per entry: "<ref>@{<timestamp>}", new oid, name, email and
+/// "<action>: <message>". Old ids are left unset.
+fn parse_reflog(output: &str, reference: &str, skip: u32) -> Vec<ReflogEntry> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks_exact(5)
+        .enumerate()
+        .map(|(index, entry)| {
+            let timestamp = entry[0]
+                .rsplit_once("@{")
+                .and_then(|(_, date)| date.trim_end_matches('}').parse().ok())
+                .unwrap_or(0);
+            let (action, message) = entry[4].split_once(": ").unwrap_or((entry[4], ""));
+
+            ReflogEntry {
+                selector: format!("{}@{{{}}}", reference, skip as usize + index),
+                old_oid: None,
+                new_oid: entry[1].to_string(),
+                action: action.to_string(),
+                message: message.to_string(),
+                committer_name: entry[2].to_string(),
+                committer_email: entry[3].to_string(),
+                timestamp,
+            }
+        })
+        .collect()
+}
+
+/// Create a branch at the commit of a reflog entry, optionally checking it out
+pub fn create_branch_from_reflog(
+    executor: &GitExecutor,
+    name: &str,
+    oid: &str,
+    checkout: bool,
+) -> Result<(), GitError> {
+    let valid = !name.starts_with('-') && executor.execute(&["check-ref-format", "--branch", name])?.exit_code == 0;
+    if !valid {
+        return Err(GitError::ParseError {
+            message: format!("Invalid branch name: {}", name),
+        });
+    }
+    let oid = resolve_commit(executor, oid)?;
+
+    let exists = executor.execute(&["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", name)])?;
+    if exists.exit_code == 0 {
+        return Err(GitError::BranchExists { name: name.to_string() });
+    }
+
+    if checkout {
+        executor.execute_checked(&["checkout", "-b", name, &oid])?;
+    } else {
+        executor.execute_checked(&["branch", name, &oid])?;
+    }
+    Ok(())
+}
+
+/// Check out the commit of a reflog entry as a detached HEAD
+pub fn checkout_reflog_entry(executor: &GitExecutor, oid: &str) -> Result<(), GitError> {
+    let oid = resolve_commit(executor, oid)?;
+    executor.execute_checked(&["checkout", "--detach", &oid])?;
+    Ok(())
+}
+
+/// Full hash of the commit a reflog entry points to
+fn resolve_commit(executor: &GitExecutor, oid: &str) -> Result<String, GitError> {
+    let not_found = || GitError::RevisionNotFound {
+        revision: oid.to_string(),
+    };
+    if oid.starts_with('-') {
+        return Err(not_found());
+    }
+    let result = executor.execute(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", oid)])?;
+    if result.exit_code != 0 {
+        return Err(not_found());
+    }
+    Ok(result.stdout.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+
+    #[test]
+    fn finds_commits_lost_by_a_reset() {
+        let (_dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+        let lost = git(&["rev-parse", "HEAD"]).trim().to_string();
+        git(&["reset", "-q", "--hard", "HEAD~1"]);
+
+        let reflog = get_reflog(&executor, None, 10, 0).unwrap();
+        assert_eq!(reflog.len(), 3);
+        assert_eq!(reflog[0].selector, "HEAD@{0}");
+        assert_eq!(reflog[0].action, "reset");
+        assert_eq!(reflog[0].old_oid.as_deref(), Some(lost.as_str()));
+        assert_eq!(reflog[1].action, "commit");
+        assert_eq!(reflog[1].message, "second");
+        assert_eq!(reflog[2].action, "commit (initial)");
+        assert_eq!(reflog[2].old_oid, None);
+        assert!(reflog.iter().all(|e| e.timestamp > 0 && e.committer_name == "Grist Test"));
+
+        let branch_log = get_reflog(&executor, Some("main"), 1, 1).unwrap();
+        assert_eq!(branch_log.len(), 1);
+        assert_eq!(branch_log[0].selector, "main@{1}");
+        assert_eq!(branch_log[0].new_oid, lost);
+        assert!(branch_log[0].old_oid.is_some());
+
+        create_branch_from_reflog(&executor, "rescued", &lost, false).unwrap();
+        assert_eq!(git(&["rev-parse", "rescued"]).trim(), lost);
+        assert!(matches!(
+            create_branch_from_reflog(&executor, "rescued", &lost, false),
+            Err(GitError::BranchExists { .. })
+        ));
+        for name in ["-f", "bad..name"] {
+            assert!(matches!(
+                create_branch_from_reflog(&executor, name, &lost, false),
+                Err(GitError::ParseError { .. })
+            ));
+        }
+        assert!(matches!(
+            create_branch_from_reflog(&executor, "other", "--orphan", false),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+        assert!(matches!(checkout_reflog_entry(&executor, "-f"), Err(GitError::RevisionNotFound { .. })));
+        assert!(matches!(get_reflog(&executor, Some("--all"), 10, 0), Err(GitError::RevisionNotFound { .. })));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::git::error::GitError;
 use crate::git::executor::{GitExecutor, build_ssh_command};
+use crate::git::hooks::{run_hooked, HookOutput};
 use crate::git::types::Remote;
 use std::collections::HashMap;
 
@@ -118,7 +119,10 @@ pub fn pull(
     Ok(())
 }
 
-pub fn push(
+/// Push, handing the output of git and of the pre-push hook to `on_output`
+/// as it is printed
+#[allow(clippy::too_many_arguments)]
+pub fn push<F>(
     executor: &GitExecutor,
     remote: Option<&str>,
     branch: Option<&str>,
@@ -126,7 +130,11 @@ pub fn push(
     set_upstream: bool,
     push_tags: bool,
     ssh_key_path: Option<&str>,
-) -> Result<(), GitError> {
+    on_output: F,
+) -> Result<(), GitError>
+where
+    F: FnMut(HookOutput),
+{
     let mut args = vec!["push"];
     if force {
         args.push("--force");
@@ -145,10 +153,12 @@ pub fn push(
     }
 
     let env = build_auth_env(ssh_key_path);
-    if env.is_empty() {
-        executor.execute_checked(&args)?;
-    } else {
-        executor.execute_with_env_checked(&args, &env)?;
+    let result = run_hooked(executor, &args, &env, on_output)?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        });
     }
     Ok(())
 }
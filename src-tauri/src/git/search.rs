@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::commit_cache::with_commit_cache;
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::log::{get_commits, stream_commit_log, LogQuery};
+use crate::git::types::Commit;
+
+/// Where a commit sits in the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitLocation {
+    pub hash: String,
+    /// Index in the log ordering, None if the query does not list the commit
+    pub position: Option<u32>,
+    /// Page containing the commit, the `skip` to load it is `page * page_size`
+    pub page: Option<u32>,
+}
+
+/// Resolve a revision expression (hash prefix, ref, `HEAD~3`, `v1.2^{}`) to
+/// a full commit hash
+pub fn resolve_revision(executor: &GitExecutor, revision: &str) -> Result<String, GitError> {
+    let not_found = || GitError::RevisionNotFound {
+        revision: revision.to_string(),
+    };
+    // Never let the expression be read as an option
+    if revision.starts_with('-') {
+        return Err(not_found());
+    }
+
+    let result = executor.execute(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", revision)])?;
+    if result.exit_code != 0 {
+        return Err(not_found());
+    }
+    Ok(result.stdout.trim().to_string())
+}
+
+/// Resolve a revision and find its position in the log of `query`
+pub fn locate_commit(
+    executor: &GitExecutor,
+    revision: &str,
+    query: &LogQuery,
+    page_size: u32,
+) -> Result<CommitLocation, GitError> {
+    let hash = resolve_revision(executor, revision)?;
+
+    let mut index = 0u32;
+    let mut position = None;
+    stream_commit_log(executor, query, "%H", |line| {
+        if position.is_none() && line == hash {
+            position = Some(index);
+        }
+        index += 1;
+    })?;
+
+    Ok(CommitLocation {
+        page: position.map(|p| p / page_size.max(1)),
+        position,
+        hash,
+    })
+}
+
+/// Find commits whose hash starts with `text` or whose subject matches it
+/// fuzzily, best matches first
+pub fn search_commits(
+    executor: &GitExecutor,
+    text: &str,
+    query: &LogQuery,
+    limit: usize,
+) -> Result<Vec<Commit>, GitError> {
+    let pattern = text.trim().to_lowercase();
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // (score, log index, hash); the log index keeps newer commits first on ties
+    let mut matches: Vec<(u32, u32, String)> = Vec::new();
+    let mut index = 0u32;
+    stream_commit_log(executor, query, "%H%x00%s", |line| {
+        let (hash, subject) = line.split_once('\0').unwrap_or((line, ""));
+        if let Some(score) = match_score(hash, subject, &pattern) {
+            matches.push((score, index, hash.to_string()));
+        }
+        index += 1;
+    })?;
+
+    get_commits(executor, &best_matches(matches, limit))
+}
+
+/// Same as `locate_commit` for the unfiltered history, read from the commit cache
+pub fn locate_cached_commit(
+    executor: &GitExecutor,
+    cache_dir: &Path,
+    revision: &str,
+    page_size: u32,
+) -> Result<CommitLocation, GitError> {
+    let hash = resolve_revision(executor, revision)?;
+    let position = with_commit_cache(executor, cache_dir, |cache| cache.position(&hash))?.map(|p| p as u32);
+
+    Ok(CommitLocation {
+        page: position.map(|p| p / page_size.max(1)),
+        position,
+        hash,
+    })
+}
+
+/// Same as `search_commits` for the unfiltered history, read from the commit cache
+pub fn search_cached_commits(
+    executor: &GitExecutor,
+    cache_dir: &Path,
+    text: &str,
+    limit: usize,
+) -> Result<Vec<Commit>, GitError> {
+    let pattern = text.trim().to_lowercase();
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    with_commit_cache(executor, cache_dir, |cache| {
+        let matches = cache
+            .commits()
+            .enumerate()
+            .filter_map(|(index, commit)| {
+                match_score(&commit.hash, &commit.subject, &pattern).map(|score| (score, index as u32, commit.hash.clone()))
+            })
+            .collect();
+        best_matches(matches, limit)
+            .iter()
+            .filter_map(|hash| cache.get(hash))
+            .collect()
+    })
+}
+
+/// Hashes of the `limit` best (score, log index, hash) matches
+fn best_matches(mut matches: Vec<(u32, u32, String)>, limit: usize) -> Vec<String> {
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().take(limit).map(|(_, _, hash)| hash).collect()
+}
+
+/// Score a commit against a lowercase pattern, higher is better
+fn match_score(hash: &str, subject: &str, pattern: &str) -> Option<u32> {
+    if pattern.len() >= 4 && hash.starts_with(pattern) {
+        return Some(3000);
+    }
+
+    let subject = subject.to_lowercase();
+    if let Some(at) = subject.find(pattern) {
+        // Earlier matches rank higher
+        return Some(2000 - at.min(999) as u32);
+    }
+
+    fuzzy_score(&subject, pattern)
+}
+
+/// Match the pattern characters in order, ranking tighter matches higher
+fn fuzzy_score(text: &str, pattern: &str) -> Option<u32> {
+    let mut chars = text.chars();
+    let mut gaps = 0u32;
+    for wanted in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let mut skipped = 0;
+        loop {
+            match chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+        gaps += skipped;
+    }
+    Some(1000 - gaps.min(999))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use crate::git::log::RefSelection;
+
+    #[test]
+    fn fuzzy_matching_ranks_tighter_matches_first() {
+        assert!(fuzzy_score("fix login redirect", "fxlogin").is_some());
+        assert!(fuzzy_score("fix login redirect", "zz").is_none());
+        assert!(fuzzy_score("add parser", "adpr").unwrap() > fuzzy_score("add a new parser", "adpr").unwrap());
+        assert_eq!(match_score("abcdef12", "anything", "abcd"), Some(3000));
+        // Too short to be taken as a hash prefix
+        assert_eq!(match_score("abcdef12", "anything", "ab"), None);
+    }
+
+    #[test]
+    fn locates_revisions_in_the_log() {
+        let (_dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        for subject in ["first", "second", "third", "fourth", "fifth"] {
+            git(&["commit", "-q", "--allow-empty", "-m", subject]);
+        }
+        git(&["tag", "-a", "v1.0", "-m", "release", "HEAD~3"]);
+
+        let query = LogQuery::default();
+        let location = locate_commit(&executor, "v1.0^{}", &query, 2).unwrap();
+        assert_eq!((location.position, location.page), (Some(3), Some(1)));
+        let location = locate_commit(&executor, &location.hash[..7], &query, 2).unwrap();
+        assert_eq!(location.position, Some(3));
+
+        assert!(matches!(
+            locate_commit(&executor, "no-such-branch", &query, 2),
+            Err(GitError::RevisionNotFound { .. })
+        ));
+
+        let results = search_commits(&executor, "frth", &query, 10).unwrap();
+        assert_eq!(results[0].subject, "fourth");
+        let results = search_commits(
+            &executor,
+            "fi",
+            &LogQuery {
+                refs: RefSelection::Current,
+                ..Default::default()
+            },
+            10,
+        )
+        .unwrap();
+        let subjects: Vec<&str> = results.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["fifth", "first"]);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+use crate::git::types::{CommitSignature, SignatureFormat, SignatureStatus};
+
+/// Format: hash, status, signer, key id, fingerprint, primary key fingerprint
+const SIGNATURE_FORMAT: &str = "%H%x00%G?%x00%GS%x00%GK%x00%GF%x00%GP%x00---END---";
+
+/// Verify the signatures of the given commits. Verification runs gpg or
+/// ssh-keygen for each signed commit, so it is kept out of log paging.
+pub fn get_commit_signatures(
+    executor: &GitExecutor,
+    hashes: &[String],
+) -> Result<HashMap<String, CommitSignature>, GitError> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // Never let a hash be read as an option
+    if let Some(option) = hashes.iter().find(|h| h.starts_with('-')) {
+        return Err(GitError::RevisionNotFound {
+            revision: option.clone(),
+        });
+    }
+
+    let formats = signature_formats(executor, hashes)?;
+
+    let mut args = vec!["log", "--no-walk=unsorted"];
+    let format_arg = format!("--format={}", SIGNATURE_FORMAT);
+    args.push(&format_arg);
+    args.extend(hashes.iter().map(|h| h.as_str()));
+    args.push("--");
+    // Unverifiable signatures are reported on stderr, only stdout matters
+    let result = executor.execute(&args)?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        });
+    }
+
+    let mut signatures = HashMap::new();
+    for entry in result.stdout.split("---END---") {
+        let parts: Vec<&str> = entry.trim_start_matches('\n').split('\0').collect();
+        if parts.len() < 6 {
+            continue;
+        }
+        let format = formats.get(parts[0]).copied();
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+
+        signatures.insert(
+            parts[0].to_string(),
+            CommitSignature {
+                status: parse_status(parts[1], format.is_some()),
+                format,
+                signer: non_empty(parts[2]),
+                key_id: non_empty(parts[3]),
+                fingerprint: non_empty(parts[4]),
+                primary_fingerprint: non_empty(parts[5]),
+            },
+        );
+    }
+
+    Ok(signatures)
+}
+
+/// Map the `%G?` letter. git reports "N" both for unsigned commits and for
+/// SSH signatures it cannot check because no allowed signers file is set.
+fn parse_status(code: &str, signed: bool) -> SignatureStatus {
+    match code {
+        "G" => SignatureStatus::Good,
+        "U" => SignatureStatus::Untrusted,
+        "B" => SignatureStatus::Bad,
+        "X" => SignatureStatus::Expired,
+        "Y" => SignatureStatus::ExpiredKey,
+        "R" => SignatureStatus::Revoked,
+        "E" => SignatureStatus::UnknownKey,
+        _ if signed => SignatureStatus::UnknownKey,
+        _ => SignatureStatus::None,
+    }
+}
+
+/// Read the signature header of each commit object to tell signed commits
+/// apart and find the kind of signature
+fn signature_formats(
+    executor: &GitExecutor,
+    hashes: &[String],
+) -> Result<HashMap<String, SignatureFormat>, GitError> {
+    let input: String = hashes.iter().map(|h| format!("{}\n", h)).collect();
+    let output = executor.execute_raw_with_stdin(&["cat-file", "--batch"], input.as_bytes())?;
+
+    let mut formats = HashMap::new();
+    let mut rest = output.as_slice();
+    // Each object is "<oid> <type> <size>\n<content>\n"
+    while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&rest[..newline]).to_string();
+        rest = &rest[newline + 1..];
+
+        let fields: Vec<&str> = header.split(' ').collect();
+        let Some(size) = fields.get(2).and_then(|s| s.parse::<usize>().ok()) else {
+            // "<name> missing"
+            continue;
+        };
+        let content = &rest[..size.min(rest.len())];
+        rest = rest.get(size + 1..).unwrap_or_default();
+
+        if fields[1] != "commit" {
+            continue;
+        }
+        if let Some(format) = header_signature_format(content) {
+            formats.insert(fields[0].to_string(), format);
+        }
+    }
+
+    Ok(formats)
+}
+
+fn header_signature_format(content: &[u8]) -> Option<SignatureFormat> {
+    let text = String::from_utf8_lossy(content);
+    let headers = text.split("\n\n").next()?;
+    let signature = headers.lines().find_map(|line| {
+        line.strip_prefix("gpgsig ")
+            .or_else(|| line.strip_prefix("gpgsig-sha256 "))
+    })?;
+
+    Some(match signature.trim() {
+        "-----BEGIN SSH SIGNATURE-----" => SignatureFormat::Ssh,
+        "-----BEGIN SIGNED MESSAGE-----" => SignatureFormat::X509,
+        _ => SignatureFormat::Gpg,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+    use std::process::Command;
+
+    #[test]
+    fn reports_ssh_signatures() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let key = dir.path().join("key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f", key.to_str().unwrap()])
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            // ssh-keygen is not installed
+            return;
+        }
+        git(&["config", "gpg.format", "ssh"]);
+        git(&["config", "user.signingkey", &format!("{}.pub", key.display())]);
+        git(&["commit", "-q", "--allow-empty", "-S", "-m", "signed"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "unsigned"]);
+        let hashes: Vec<String> = git(&["rev-list", "HEAD"]).lines().map(|l| l.to_string()).collect();
+
+        // Signed, but nothing to check it against yet
+        let signatures = get_commit_signatures(&executor, &hashes).unwrap();
+        assert_eq!(signatures[&hashes[0]].status, SignatureStatus::None);
+        assert_eq!(signatures[&hashes[1]].status, SignatureStatus::UnknownKey);
+        assert_eq!(signatures[&hashes[1]].format, Some(SignatureFormat::Ssh));
+
+        let public_key = std::fs::read_to_string(format!("{}.pub", key.display())).unwrap();
+        let allowed = dir.path().join("allowed_signers");
+        std::fs::write(&allowed, format!("test@example.com {}", public_key)).unwrap();
+        git(&["config", "gpg.ssh.allowedSignersFile", allowed.to_str().unwrap()]);
+
+        let signatures = get_commit_signatures(&executor, &hashes).unwrap();
+        let signature = &signatures[&hashes[1]];
+        assert_eq!(signature.status, SignatureStatus::Good);
+        assert_eq!(signature.signer.as_deref(), Some("test@example.com"));
+        assert!(signature.fingerprint.as_deref().unwrap().starts_with("SHA256:"));
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::{CommandResult, GitExecutor};
+use crate::git::types::SignatureFormat;
+
+/// Signing settings of the repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningConfig {
+    /// gpg.format, openpgp when not set
+    pub format: SignatureFormat,
+    /// user.signingkey: a key id for openpgp, a key file or "key::<public key>" for ssh
+    pub key: Option<String>,
+    /// commit.gpgSign, also used by merges and cherry-picks
+    pub sign_commits: bool,
+    /// tag.gpgSign
+    pub sign_tags: bool,
+}
+
+/// Whether and how to sign a commit, merge, cherry-pick or tag
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignOptions {
+    /// Sign when true, never sign when false, follow commit.gpgSign or
+    /// tag.gpgSign when None
+    pub sign: Option<bool>,
+    /// Key used instead of user.signingkey
+    pub key: Option<String>,
+}
+
+impl SignOptions {
+    /// Options going before the git subcommand
+    pub fn config_args(&self) -> Vec<String> {
+        match self.key {
+            Some(ref key) => vec!["-c".to_string(), format!("user.signingkey={}", key)],
+            None => Vec::new(),
+        }
+    }
+
+    /// Flag of `git commit`, `merge` and `cherry-pick`
+    pub fn commit_flag(&self) -> Option<&'static str> {
+        self.sign.map(|sign| if sign { "-S" } else { "--no-gpg-sign" })
+    }
+
+    /// Flag of `git tag`
+    pub fn tag_flag(&self) -> Option<&'static str> {
+        self.sign.map(|sign| if sign { "-s" } else { "--no-sign" })
+    }
+}
+
+pub fn get_signing_config(executor: &GitExecutor) -> Result<SigningConfig, GitError> {
+    let get = |key: &str| -> Result<Option<String>, GitError> {
+        let result = executor.execute(&["config", "--get", key])?;
+        Ok((result.exit_code == 0).then(|| result.stdout.trim().to_string()))
+    };
+    let get_bool = |key: &str| -> Result<bool, GitError> {
+        let result = executor.execute(&["config", "--type=bool", "--get", key])?;
+        Ok(result.exit_code == 0 && result.stdout.trim() == "true")
+    };
+
+    let format = match get("gpg.format")?.as_deref() {
+        Some("ssh") => SignatureFormat::Ssh,
+        Some("x509") => SignatureFormat::X509,
+        _ => SignatureFormat::Gpg,
+    };
+
+    Ok(SigningConfig {
+        format,
+        key: get("user.signingkey")?,
+        sign_commits: get_bool("commit.gpgSign")?,
+        sign_tags: get_bool("tag.gpgSign")?,
+    })
+}
+
+/// Private key file of an SSH signing key, None for literal keys and public
+/// keys without their private half (those are signed with by ssh-agent)
+pub fn ssh_private_key_path(key: &str) -> Option<PathBuf> {
+    if key.starts_with("key::") || key.starts_with("ssh-") {
+        return None;
+    }
+
+    let path = match key.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var_os("HOME")?).join(rest),
+        None => PathBuf::from(key),
+    };
+    let private = match path.to_str().and_then(|p| p.strip_suffix(".pub")) {
+        Some(stem) => PathBuf::from(stem),
+        None => path,
+    };
+    private.is_file().then_some(private)
+}
+
+/// Turn a failed command into SigningFailed when signing is what failed,
+/// going by the messages git prints for gpg, x509 and ssh signing only
+pub fn signing_error(result: &CommandResult) -> Option<GitError> {
+    const MARKERS: [&str; 7] = [
+        "gpg failed to sign the data",
+        "unable to sign the tag",
+        "Couldn't load public key",
+        "No private key found for",
+        "either user.signingkey or gpg.ssh.defaultKeyCommand needs to be configured",
+        "ssh-keygen -Y sign is needed for ssh signing",
+        "failed to get the ssh fingerprint for key",
+    ];
+    if !MARKERS.iter().any(|m| result.stderr.contains(m)) {
+        return None;
+    }
+
+    let message = result
+        .stderr
+        .lines()
+        .map(|l| l.trim_start_matches("error: ").trim_start_matches("fatal: ").trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("Enter passphrase"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(GitError::SigningFailed { message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::branch::merge_branch;
+    use crate::git::commit::{cherry_pick, create_commit, CommitOptions};
+    use crate::git::signature::get_commit_signatures;
+    use crate::git::tag::create_tag;
+    use crate::git::test_util::init_repo_at;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    #[test]
+    fn signs_commits_tags_and_picks_with_ssh_keys() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let executor = init_repo_at(&repo);
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let key = dir.path().join("key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f", key.to_str().unwrap()])
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            // ssh-keygen is not installed
+            return;
+        }
+
+        git(&["config", "gpg.format", "ssh"]);
+        git(&["config", "user.signingkey", "/nonexistent/key.pub"]);
+
+        let config = get_signing_config(&executor).unwrap();
+        assert_eq!(config.format, SignatureFormat::Ssh);
+        assert!(!config.sign_commits);
+        assert_eq!(ssh_private_key_path(&format!("{}.pub", key.display())), Some(key.clone()));
+        assert_eq!(ssh_private_key_path("key::ssh-ed25519 AAAA"), None);
+
+        let mut options = CommitOptions {
+            allow_empty: true,
+            signing: SignOptions {
+                sign: Some(true),
+                key: None,
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            create_commit(&executor, "signed", &options, |_| {}),
+            Err(GitError::SigningFailed { .. })
+        ));
+
+        // The key given with the options wins over user.signingkey
+        options.signing.key = Some(key.to_string_lossy().to_string());
+        create_commit(&executor, "signed", &options, |_| {}).unwrap();
+        let tag_signing = options.signing.clone();
+        create_tag(&executor, "v1", None, Some("release"), &tag_signing).unwrap();
+        assert!(git(&["cat-file", "-p", "v1"]).contains("-----BEGIN SSH SIGNATURE-----"));
+        assert!(matches!(
+            create_tag(&executor, "v2", None, None, &tag_signing),
+            Err(GitError::ParseError { .. })
+        ));
+        // tag.gpgSign asks for a message too, unless signing is turned off
+        git(&["config", "tag.gpgSign", "true"]);
+        assert!(matches!(
+            create_tag(&executor, "v2", None, None, &SignOptions::default()),
+            Err(GitError::ParseError { .. })
+        ));
+        let unsigned = SignOptions {
+            sign: Some(false),
+            key: None,
+        };
+        create_tag(&executor, "v2", None, None, &unsigned).unwrap();
+        assert_eq!(git(&["cat-file", "-t", "v2"]).trim(), "commit");
+
+        // Failures unrelated to signing are left alone
+        let result = CommandResult {
+            stdout: String::new(),
+            stderr: "error: insufficient permission for adding an object\nfatal: failed to write commit object\n".into(),
+            exit_code: 128,
+        };
+        assert!(signing_error(&result).is_none());
+
+        git(&["checkout", "-q", "-b", "topic"]);
+        std::fs::write(repo.join("picked.txt"), "picked\n").unwrap();
+        git(&["add", "picked.txt"]);
+        git(&["commit", "-q", "-m", "unsigned"]);
+        let unsigned = git(&["rev-parse", "HEAD"]).trim().to_string();
+        git(&["commit", "-q", "--allow-empty", "-m", "to merge"]);
+        git(&["checkout", "-q", "main"]);
+        cherry_pick(&executor, &unsigned, &tag_signing).unwrap();
+        merge_branch(&executor, "topic", true, &tag_signing, |_| {}).unwrap();
+
+        // Signed, with no allowed signers file to check them against
+        let hashes: Vec<String> = git(&["rev-list", "--first-parent", "main"]).lines().map(|l| l.to_string()).collect();
+        assert_eq!(hashes.len(), 3);
+        let signatures = get_commit_signatures(&executor, &hashes).unwrap();
+        assert!(hashes.iter().all(|h| signatures[h].format == Some(SignatureFormat::Ssh)));
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::error::GitError;
+use crate::git::executor::GitExecutor;
+
+/// Hotspots returned when the query does not set a limit
+const DEFAULT_HOTSPOT_LIMIT: usize = 20;
+
+/// What `get_repository_stats` aggregates over
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsQuery {
+    /// Revision range such as "v1.0..main", HEAD when not set
+    pub range: Option<String>,
+    /// Only commits and files under these paths
+    pub paths: Vec<String>,
+    /// Author date bounds in any format git accepts. git itself filters on
+    /// committer dates, so commits are filtered after they are read.
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Number of files listed in `hotspots`
+    pub hotspot_limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LineStats {
+    pub commits: u32,
+    pub additions: u64,
+    pub deletions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorStats {
+    /// Identity after applying .mailmap
+    pub name: String,
+    pub email: String,
+    /// "Name <email>" identities recorded in the commits, before .mailmap
+    pub raw_identities: Vec<String>,
+    #[serde(flatten)]
+    pub lines: LineStats,
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+}
+
+/// Activity of one day or of the week starting on `date` (a Monday)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityBucket {
+    /// "YYYY-MM-DD" in the author's time zone
+    pub date: String,
+    #[serde(flatten)]
+    pub lines: LineStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChurn {
+    pub path: String,
+    #[serde(flatten)]
+    pub lines: LineStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepositoryStats {
+    pub total: LineStats,
+    /// Most commits first
+    pub authors: Vec<AuthorStats>,
+    /// Oldest first, days and weeks without commits are left out
+    pub daily: Vec<ActivityBucket>,
+    pub weekly: Vec<ActivityBucket>,
+    /// Files changed by the most commits first
+    pub hotspots: Vec<FileChurn>,
+}
+
+/// Aggregate commits, line counts and file churn per author, day and week.
+/// Merge commits are left out so their changes are not counted twice.
+pub fn get_repository_stats(executor: &GitExecutor, query: &StatsQuery) -> Result<RepositoryStats, GitError> {
+    let range = query.range.as_deref().unwrap_or("HEAD");
+    // Never let the range be read as an option
+    if range.starts_with('-') {
+        return Err(GitError::RevisionNotFound {
+            revision: range.to_string(),
+        });
+    }
+
+    let mut args: Vec<String> = vec![
+        "-c".into(),
+        "core.quotePath=false".into(),
+        "log".into(),
+        "--no-merges".into(),
+        "--no-renames".into(),
+        "--numstat".into(),
+        "--date=short".into(),
+        // %aN and %aE apply .mailmap, %an and %ae are as recorded
+        "--format=%x1e%aN%x00%aE%x00%ad%x00%at%x00%an%x00%ae".into(),
+    ];
+    args.push(range.to_string());
+    args.push("--".into());
+    args.extend(query.paths.iter().cloned());
+
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = executor.execute_checked(&args)?;
+
+    let since = query.since.as_deref().map(|date| date_timestamp(executor, "since", date)).transpose()?;
+    let until = query.until.as_deref().map(|date| date_timestamp(executor, "until", date)).transpose()?;
+    let in_range = |timestamp: i64| since.is_none_or(|s| timestamp >= s) && until.is_none_or(|u| timestamp <= u);
+    Ok(aggregate(&output, query.hotspot_limit.unwrap_or(DEFAULT_HOTSPOT_LIMIT), in_range))
+}
+
+/// Timestamp of a `--since` or `--until` date, read the way git log reads it
+fn date_timestamp(executor: &GitExecutor, bound: &str, date: &str) -> Result<i64, GitError> {
+    let output = executor.execute_checked(&["rev-parse", &format!("--{}={}", bound, date)])?;
+    // "--max-age=<timestamp>" for since, "--min-age=<timestamp>" for until
+    output
+        .trim()
+        .split_once('=')
+        .and_then(|(_, timestamp)| timestamp.parse().ok())
+        .ok_or_else(|| GitError::ParseError {
+            message: format!("Invalid date: {}", date),
+        })
+}
+
+/// Aggregate the commits of `output` whose author timestamp is `in_range`
+fn aggregate<F>(output: &str, hotspot_limit: usize, in_range: F) -> RepositoryStats
+where
+    F: Fn(i64) -> bool,
+{
+    let mut total = LineStats::default();
+    let mut authors: HashMap<(String, String), AuthorStats> = HashMap::new();
+    let mut daily: HashMap<String, LineStats> = HashMap::new();
+    let mut weekly: HashMap<String, LineStats> = HashMap::new();
+    let mut files: HashMap<String, LineStats> = HashMap::new();
+
+    for record in output.split('\x1e').skip(1) {
+        let mut lines = record.lines();
+        let header: Vec<&str> = lines.next().unwrap_or_default().split('\0').collect();
+        if header.len() < 6 {
+            continue;
+        }
+        let (name, email, date) = (header[0], header[1], header[2]);
+        let timestamp: i64 = header[3].parse().unwrap_or(0);
+        if !in_range(timestamp) {
+            continue;
+        }
+        let raw_identity = format!("{} <{}>", header[4], header[5]);
+
+        let mut commit = LineStats {
+            commits: 1,
+            ..Default::default()
+        };
+        for line in lines.filter(|l| !l.is_empty()) {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(added), Some(deleted), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            // Binary files show "-" for both counts
+            let added = added.parse().unwrap_or(0);
+            let deleted = deleted.parse().unwrap_or(0);
+            commit.additions += added;
+            commit.deletions += deleted;
+
+            let file = files.entry(path.to_string()).or_default();
+            file.commits += 1;
+            file.additions += added;
+            file.deletions += deleted;
+        }
+
+        add_lines(&mut total, &commit);
+        add_lines(daily.entry(date.to_string()).or_default(), &commit);
+        if let Some(week) = week_start(date) {
+            add_lines(weekly.entry(week).or_default(), &commit);
+        }
+
+        let author = authors
+            .entry((name.to_string(), email.to_string()))
+            .or_insert_with(|| AuthorStats {
+                name: name.to_string(),
+                email: email.to_string(),
+                raw_identities: Vec::new(),
+                lines: LineStats::default(),
+                first_timestamp: timestamp,
+                last_timestamp: timestamp,
+            });
+        add_lines(&mut author.lines, &commit);
+        if !author.raw_identities.contains(&raw_identity) {
+            author.raw_identities.push(raw_identity);
+        }
+        author.first_timestamp = author.first_timestamp.min(timestamp);
+        author.last_timestamp = author.last_timestamp.max(timestamp);
+    }
+
+    let mut authors: Vec<AuthorStats> = authors.into_values().collect();
+    authors.sort_by(|a, b| b.lines.commits.cmp(&a.lines.commits).then_with(|| a.name.cmp(&b.name)));
+
+    let buckets = |map: HashMap<String, LineStats>| {
+        let mut buckets: Vec<ActivityBucket> = map
+            .into_iter()
+            .map(|(date, lines)| ActivityBucket { date, lines })
+            .collect();
+        buckets.sort_by(|a, b| a.date.cmp(&b.date));
+        buckets
+    };
+
+    let mut hotspots: Vec<FileChurn> = files.into_iter().map(|(path, lines)| FileChurn { path, lines }).collect();
+    hotspots.sort_by(|a, b| {
+        b.lines
+            .commits
+            .cmp(&a.lines.commits)
+            .then_with(|| (b.lines.additions + b.lines.deletions).cmp(&(a.lines.additions + a.lines.deletions)))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    hotspots.truncate(hotspot_limit);
+
+    RepositoryStats {
+        total,
+        authors,
+        daily: buckets(daily),
+        weekly: buckets(weekly),
+        hotspots,
+    }
+}
+
+fn add_lines(into: &mut LineStats, commit: &LineStats) {
+    into.commits += commit.commits;
+    into.additions += commit.additions;
+    into.deletions += commit.deletions;
+}
+
+/// Monday of the week of a "YYYY-MM-DD" date
+fn week_start(date: &str) -> Option<String> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+
+    let days = days_from_civil(year, month, day);
+    // 1970-01-01 was a Thursday
+    let monday = days - (days + 3).rem_euclid(7);
+    let (year, month, day) = civil_from_days(monday);
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_util::init_repo;
+
+    #[test]
+    fn weeks_start_on_monday() {
+        assert_eq!(week_start("2024-01-03").as_deref(), Some("2024-01-01"));
+        assert_eq!(week_start("2024-01-01").as_deref(), Some("2024-01-01"));
+        assert_eq!(week_start("2023-01-01").as_deref(), Some("2022-12-26"));
+        assert_eq!(week_start("2024-03-03").as_deref(), Some("2024-02-26"));
+        assert_eq!(week_start("garbage"), None);
+    }
+
+    #[test]
+    fn aggregates_authors_activity_and_churn() {
+        let (dir, executor) = init_repo();
+        let git = |args: &[&str]| executor.execute_checked(args).unwrap();
+        let commit_as = |author: &str, date: &str, file: &str, content: &str| {
+            std::fs::write(dir.path().join(file), content).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", file, "--author", author, "--date", date]);
+        };
+
+        commit_as("Ann <ann@old.example>", "2024-01-01T10:00:00+00:00", "a.txt", "1\n2\n");
+        commit_as("Ann Lee <ann@example.com>", "2024-01-03T10:00:00+00:00", "a.txt", "1\n");
+        commit_as("Bob <bob@example.com>", "2024-01-09T10:00:00+00:00", "b.txt", "x\n");
+        std::fs::write(dir.path().join(".mailmap"), "Ann Lee <ann@example.com> <ann@old.example>\n").unwrap();
+
+        let stats = get_repository_stats(&executor, &StatsQuery::default()).unwrap();
+        assert_eq!(
+            stats.total,
+            LineStats {
+                commits: 3,
+                additions: 3,
+                deletions: 1
+            }
+        );
+        assert_eq!(stats.authors.len(), 2);
+        assert_eq!((stats.authors[0].name.as_str(), stats.authors[0].lines.commits), ("Ann Lee", 2));
+        assert_eq!(stats.authors[0].raw_identities.len(), 2);
+        let weeks: Vec<(&str, u32)> = stats.weekly.iter().map(|w| (w.date.as_str(), w.lines.commits)).collect();
+        assert_eq!(weeks, vec![("2024-01-01", 2), ("2024-01-08", 1)]);
+        assert_eq!(stats.daily.len(), 3);
+        assert_eq!(stats.hotspots[0].path, "a.txt");
+        assert_eq!(stats.hotspots[0].lines.commits, 2);
+
+        let filtered = get_repository_stats(
+            &executor,
+            &StatsQuery {
+                paths: vec!["b.txt".into()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(filtered.total.commits, 1);
+        assert_eq!(filtered.authors[0].email, "bob@example.com");
+
+        // Bounds apply to author dates, the commits were all committed today
+        let bounded = get_repository_stats(
+            &executor,
+            &StatsQuery {
+                since: Some("2024-01-02T00:00:00+00:00".into()),
+                until: Some("2024-01-05T00:00:00+00:00".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(bounded.total.commits, 1);
+        assert_eq!(bounded.daily[0].date, "2024-01-03");
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use crate::git::executor::GitExecutor;
+use crate::git::log::get_range_log;
+use crate::git::types::*;
+
+/// Most commits listed on each side of a submodule change
+const MAX_SUBMODULE_COMMITS: u32 = 100;
+
+/// Read the recorded commits from the "Subproject commit" lines of a gitlink diff
+pub fn detect_submodule_change(diff: &mut FileDiff) {
+    let mut change = SubmoduleChange::default();
+
+    for line in diff.hunks.iter().flat_map(|h| h.lines.iter()) {
+        let Some(commit) = line.content.strip_prefix("Subproject commit ") else {
+            continue;
+        };
+        let (commit, dirty) = match commit.strip_suffix("-dirty") {
+            Some(commit) => (commit, true),
+            None => (commit, false),
+        };
+        match line.line_type {
+            DiffLineType::Deletion => change.old_commit = Some(commit.to_string()),
+            DiffLineType::Addition => {
+                change.new_commit = Some(commit.to_string());
+                change.dirty = dirty;
+            }
+            _ => {}
+        }
+    }
+
+    diff.is_binary = false;
+    diff.submodule = Some(change);
+}
+
+/// List the commits between the old and new recorded commits, read from
+/// the submodule checkout
+pub fn fill_submodule_commits(executor: &GitExecutor, diff: &mut FileDiff) {
+    let Some(ref mut change) = diff.submodule else {
+        return;
+    };
+
+    let path = match (&change.new_commit, &diff.old_path) {
+        (None, Some(old_path)) => old_path.as_str(),
+        _ => diff.new_path.as_str(),
+    };
+    let sub_path = Path::new(executor.repo_path()).join(path);
+    change.initialized = sub_path.join(".git").exists();
+    if !change.initialized {
+        return;
+    }
+
+    let Some(sub_executor) = sub_path.to_str().and_then(|p| GitExecutor::new(p).ok()) else {
+        change.initialized = false;
+        return;
+    };
+
+    let has_commit = |oid: &str| {
+        sub_executor
+            .execute(&["cat-file", "-e", &format!("{}^{{commit}}", oid)])
+            .map(|r| r.exit_code == 0)
+            .unwrap_or(false)
+    };
+    let old_ok = change.old_commit.as_deref().is_none_or(has_commit);
+    let new_ok = change.new_commit.as_deref().is_none_or(has_commit);
+    change.commits_missing = !old_ok || !new_ok;
+    if change.commits_missing {
+        return;
+    }
+
+    let range_log = |range: String| get_range_log(&sub_executor, &range, MAX_SUBMODULE_COMMITS).unwrap_or_default();
+    match (change.old_commit.as_deref(), change.new_commit.as_deref()) {
+        (Some(old), Some(new)) if old != new => {
+            change.commits_added = range_log(format!("{}..{}", old, new));
+            change.commits_removed = range_log(format!("{}..{}", new, old));
+        }
+        (None, Some(new)) => change.commits_added = range_log(new.to_string()),
+        (Some(old), None) => change.commits_removed = range_log(old.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::get_commit_diff;
+    use crate::git::test_util::init_repo_at;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn commit(executor: &GitExecutor, message: &str) -> String {
+        executor.execute_checked(&["commit", "-q", "--allow-empty", "-m", message]).unwrap();
+        executor.execute_checked(&["rev-parse", "HEAD"]).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn submodule_update_lists_commits() {
+        let dir = TempDir::new().unwrap();
+        let lib = init_repo_at(&dir.path().join("lib"));
+        let first = commit(&lib, "lib: first");
+
+        let app = init_repo_at(&dir.path().join("app"));
+        let lib_url = dir.path().join("lib");
+        app.execute_checked(&[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "-q",
+            lib_url.to_str().unwrap(),
+            "lib",
+        ])
+        .unwrap();
+        commit(&app, "add lib");
+
+        let sub = GitExecutor::new(dir.path().join("app/lib").to_str().unwrap()).unwrap();
+        sub.execute_checked(&["config", "user.name", "Grist Test"]).unwrap();
+        sub.execute_checked(&["config", "user.email", "test@example.com"]).unwrap();
+        commit(&sub, "lib: second");
+        let third = commit(&sub, "lib: third");
+        app.execute_checked(&["add", "lib"]).unwrap();
+        let hash = commit(&app, "bump lib");
+
+        let diffs = get_commit_diff(&app, &hash).unwrap();
+        let change = diffs[0].submodule.as_ref().unwrap();
+        assert!(change.initialized);
+        assert_eq!(change.old_commit.as_deref(), Some(first.as_str()));
+        assert_eq!(change.new_commit.as_deref(), Some(third.as_str()));
+        let subjects: Vec<&str> = change.commits_added.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["lib: third", "lib: second"]);
+        assert!(change.commits_removed.is_empty());
+
+        // Without a checkout only the recorded commits are known
+        fs::remove_dir_all(dir.path().join("app/lib")).unwrap();
+        let diffs = get_commit_diff(&app, &hash).unwrap();
+        let change = diffs[0].submodule.as_ref().unwrap();
+        assert!(!change.initialized);
+        assert!(change.commits_added.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::git::error::GitError;
 use crate::git::executor::GitExecutor;
+use crate::git::signing::{get_signing_config, signing_error, SignOptions};
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize)]
@@ -62,8 +63,25 @@ pub fn create_tag(
     name: &str,
     commit: Option<&str>,
     message: Option<&str>,
+    signing: &SignOptions,
 ) -> Result<(), GitError> {
-    let mut args = vec!["tag"];
+    // A signed tag is annotated, git would open an editor for its message
+    let sign = match signing.sign {
+        Some(sign) => sign,
+        None => get_signing_config(executor)?.sign_tags,
+    };
+    if sign && message.is_none() {
+        return Err(GitError::ParseError {
+            message: "A signed tag needs a message".to_string(),
+        });
+    }
+
+    let config_args = signing.config_args();
+    let mut args: Vec<&str> = config_args.iter().map(|a| a.as_str()).collect();
+    args.push("tag");
+    if let Some(flag) = signing.tag_flag() {
+        args.push(flag);
+    }
 
     // If message provided, create annotated tag
     if let Some(msg) = message {
@@ -82,7 +100,13 @@ pub fn create_tag(
         }
     }
 
-    executor.execute_checked(&args)?;
+    let result = executor.execute(&args)?;
+    if result.exit_code != 0 {
+        return Err(signing_error(&result).unwrap_or(GitError::CommandFailed {
+            code: result.exit_code,
+            stderr: result.stderr,
+        }));
+    }
     Ok(())
 }
 
@@ -0,0 +1,47 @@
+//! Fixtures shared by the tests of the git modules
+
+use std::fs;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use crate::git::executor::GitExecutor;
+
+/// Smallest PNG header that image sniffing understands
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+    bytes.extend_from_slice(b"IHDR");
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+    bytes
+}
+
+/// Initialize a repository at `path` on a "main" branch, committing as
+/// "Grist Test". Signing and the user's hooks are turned off so that their
+/// global configuration does not leak in; hooks go in the empty directory
+/// that `rev-parse --git-path hooks` now points to.
+pub fn init_repo_at(path: &Path) -> GitExecutor {
+    fs::create_dir_all(path).unwrap();
+    let executor = GitExecutor::new(path.to_str().unwrap()).unwrap();
+    executor.execute_checked(&["init", "-q", "-b", "main"]).unwrap();
+    let hooks = path.join(".git/test-hooks");
+    fs::create_dir(&hooks).unwrap();
+    for (key, value) in [
+        ("user.name", "Grist Test"),
+        ("user.email", "test@example.com"),
+        ("commit.gpgsign", "false"),
+        ("tag.gpgsign", "false"),
+        ("core.hooksPath", hooks.to_str().unwrap()),
+    ] {
+        executor.execute_checked(&["config", key, value]).unwrap();
+    }
+    executor
+}
+
+/// A repository set up by `init_repo_at` in a new temporary directory
+pub fn init_repo() -> (TempDir, GitExecutor) {
+    let dir = TempDir::new().unwrap();
+    let executor = init_repo_at(dir.path());
+    (dir, executor)
+}
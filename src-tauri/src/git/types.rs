@@ -49,6 +49,7 @@ pub struct GitStatus {
 pub struct Commit {
     pub hash: String,
     pub short_hash: String,
+    /// Author and committer identities with .mailmap applied
     pub author_name: String,
     pub author_email: String,
     pub author_date: String,
@@ -61,6 +62,116 @@ pub struct Commit {
     pub body: String,
     pub parent_hashes: Vec<String>,
     pub refs: Vec<String>,
+    /// Verified signature, None until loaded with `get_commit_signatures`
+    #[serde(default)]
+    pub signature: Option<CommitSignature>,
+    /// Trailers at the end of the message (Signed-off-by, Co-authored-by, ...)
+    #[serde(default)]
+    pub trailers: Vec<Trailer>,
+    /// Size of the change, only loaded on request
+    #[serde(default)]
+    pub stats: Option<CommitStats>,
+    /// Identities as recorded in the commit, before .mailmap
+    #[serde(default)]
+    pub raw_author_name: String,
+    #[serde(default)]
+    pub raw_author_email: String,
+    #[serde(default)]
+    pub raw_committer_name: String,
+    #[serde(default)]
+    pub raw_committer_email: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CommitStats {
+    pub files_changed: u32,
+    pub insertions: u32,
+    pub deletions: u32,
+}
+
+/// A file changed by a commit, without its hunks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitFileChange {
+    /// Path after the change (the old path is in `status` for renames and copies)
+    pub path: String,
+    pub status: FileStatus,
+    pub additions: u32,
+    pub deletions: u32,
+    pub is_binary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitDetails {
+    pub commit: Commit,
+    pub files: Vec<CommitFileChange>,
+    /// Notes under the default display notes refs
+    pub notes: Vec<CommitNote>,
+}
+
+/// A "Key: value" line of the trailer block of a commit message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// A git note attached to a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitNote {
+    /// Notes ref holding the note, e.g. refs/notes/commits
+    pub notes_ref: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+    /// Valid signature from a trusted key
+    Good,
+    /// Valid signature from a key of unknown validity
+    Untrusted,
+    Bad,
+    /// Signed, but the key is missing or no verification is set up
+    UnknownKey,
+    /// Valid signature that has expired
+    Expired,
+    /// Valid signature made by a key that has since expired
+    ExpiredKey,
+    /// Valid signature made by a revoked key
+    Revoked,
+    /// Not signed
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureFormat {
+    Gpg,
+    Ssh,
+    X509,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitSignature {
+    pub status: SignatureStatus,
+    pub format: Option<SignatureFormat>,
+    /// User id of a GPG key, principal of an SSH key
+    pub signer: Option<String>,
+    pub key_id: Option<String>,
+    pub fingerprint: Option<String>,
+    /// Fingerprint of the primary key when signed with a GPG subkey
+    pub primary_fingerprint: Option<String>,
+}
+
+/// A commit in the history of a single file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileLogEntry {
+    pub commit: Commit,
+    /// Path of the file in this commit
+    pub path: String,
+    pub status: FileStatus,
+    pub additions: u32,
+    pub deletions: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,6 +210,14 @@ pub struct DiffHunk {
     pub new_lines: u32,
     pub header: String,
     pub lines: Vec<DiffLine>,
+    /// Index in the whole hunk of the first line in `lines`, non-zero when
+    /// the start of the hunk came in an earlier page. Line indices given to
+    /// `stage_lines` and friends count from the start of the whole hunk.
+    #[serde(default)]
+    pub line_offset: usize,
+    /// True if the hunk was cut by `DiffLimits` and more of its lines follow
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,6 +226,12 @@ pub struct DiffLine {
     pub old_line_number: Option<u32>,
     pub new_line_number: Option<u32>,
     pub content: String,
+    /// True if the line ends with CRLF (the carriage return is not part of `content`)
+    #[serde(default)]
+    pub crlf: bool,
+    /// True if the line is followed by "\ No newline at end of file"
+    #[serde(default)]
+    pub no_newline: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -126,4 +251,130 @@ pub struct FileDiff {
     pub is_binary: bool,
     pub additions: u32,
     pub deletions: u32,
+    /// Hash of the parsed hunks, used to check that line/hunk selections
+    /// made in the UI still match the current diff before applying them.
+    /// Taken from the diff with CR changes, which staging works on, even
+    /// when they are hidden from `hunks`.
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Size in bytes of the old and new versions, when known
+    #[serde(default)]
+    pub old_size: Option<u64>,
+    #[serde(default)]
+    pub new_size: Option<u64>,
+    /// True if only part of the content was loaded
+    #[serde(default)]
+    pub truncated: bool,
+    /// Blob ids of the old and new versions (None when the side does not exist)
+    #[serde(default)]
+    pub old_oid: Option<String>,
+    #[serde(default)]
+    pub new_oid: Option<String>,
+    /// MIME type sniffed from binary content
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// Pixel dimensions of the old and new versions of an image
+    #[serde(default)]
+    pub old_dimensions: Option<ImageDimensions>,
+    #[serde(default)]
+    pub new_dimensions: Option<ImageDimensions>,
+    /// Git LFS pointers stored in place of the old and new content
+    #[serde(default)]
+    pub old_lfs_pointer: Option<LfsPointer>,
+    #[serde(default)]
+    pub new_lfs_pointer: Option<LfsPointer>,
+    /// Number of hunks and diff lines in the whole diff, including the ones
+    /// left out by `DiffLimits`
+    #[serde(default)]
+    pub total_hunks: usize,
+    #[serde(default)]
+    pub total_lines: usize,
+    /// Set when the path is a submodule (gitlink) instead of a file
+    #[serde(default)]
+    pub submodule: Option<SubmoduleChange>,
+}
+
+/// Change of the commit recorded for a submodule
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubmoduleChange {
+    /// Commit recorded before and after the change, None when the
+    /// submodule was added or removed
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    /// The submodule checkout has uncommitted changes (working tree diffs only)
+    pub dirty: bool,
+    /// False when the submodule is not checked out, commits are then unknown
+    pub initialized: bool,
+    /// True when a recorded commit is not in the submodule repository,
+    /// usually because it was not fetched yet
+    pub commits_missing: bool,
+    /// Commits in new_commit but not old_commit, newest first
+    pub commits_added: Vec<Commit>,
+    /// Commits in old_commit but not new_commit, when the submodule was
+    /// moved back or to a different branch
+    pub commits_removed: Vec<Commit>,
+}
+
+/// Caps on how much of a diff is sent to the frontend at once.
+/// Lines are taken in order until a cap is reached, so the last hunk sent
+/// may be cut partway and is marked `truncated`. The file is then marked
+/// `truncated` too, and the rest is loaded from a `DiffCursor` (hunk and
+/// line) with `get_diff_hunks`, which returns at least one line per page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffLimits {
+    pub max_lines_per_file: Option<usize>,
+    pub max_bytes_per_file: Option<usize>,
+    /// Files past this count only carry their header, paths and counts
+    pub max_files: Option<usize>,
+}
+
+/// Position in a file diff: a hunk and a line inside it
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct DiffCursor {
+    pub hunk: usize,
+    pub line: usize,
+}
+
+/// Further hunks of a file diff fetched after a truncated first load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunkPage {
+    /// Index of the first returned hunk in the whole diff
+    pub start_hunk: usize,
+    /// Line of that hunk the page starts at
+    pub start_line: usize,
+    pub hunks: Vec<DiffHunk>,
+    pub total_hunks: usize,
+    /// True if lines remain after this page
+    pub has_more: bool,
+    /// Where the next page starts, None on the last page
+    pub next: Option<DiffCursor>,
+    /// Fingerprint of the whole diff, differs from the first load if the
+    /// file changed in between
+    pub fingerprint: String,
+}
+
+/// Which diff a follow-up hunk request refers to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum DiffTarget {
+    Unstaged,
+    Staged,
+    Untracked,
+    Commit { hash: String },
+    Stash { index: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LfsPointer {
+    /// SHA-256 of the real content
+    pub oid: String,
+    /// Size in bytes of the real content
+    pub size: u64,
 }
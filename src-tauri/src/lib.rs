@@ -6,19 +6,31 @@ mod keys;
 use commands::{
     get_git_status, get_git_version, get_git_path, test_git_path, get_repository_info, is_git_repository, open_repository,
     stage_file, stage_all, unstage_file, unstage_all, discard_changes,
-    get_file_diff, get_commit_diff, get_stash_diff, get_untracked_file_diff, stage_lines, unstage_lines,
+    get_file_diff, get_commit_diff, get_file_diff_at_commit, get_stash_diff, get_untracked_file_diff, stage_lines, unstage_lines,
+    get_diff_hunks,
+    discard_lines, stage_hunks, unstage_hunks, discard_hunks,
     get_blob_base64,
-    create_commit, get_last_commit_message, cherry_pick, revert_commit,
-    abort_cherry_pick, abort_revert, continue_cherry_pick, continue_revert,
-    get_commit_log,
+    create_commit, create_fixup_commit, get_last_commit_message, cherry_pick, cherry_pick_commits, revert_commit,
+    abort_cherry_pick, abort_revert, continue_cherry_pick, skip_cherry_pick, continue_revert,
+    list_hooks, set_hook_enabled,
+    get_commit_templates, lint_commit_message,
+    get_commit_log, get_commit_details, get_commit_graph, get_file_log, get_commit_signatures,
+    locate_commit, search_commits,
+    blame_file, blame_file_incremental,
+    get_repository_stats,
+    get_mailmap, set_mailmap, check_mailmap,
+    get_commit_notes, get_display_notes_refs, add_note, edit_note, remove_note,
     get_branches, create_branch, delete_branch, checkout_branch, rename_branch, delete_remote_branch,
-    merge_branch, rebase_branch, abort_merge, abort_rebase, continue_rebase, continue_merge,
+    merge_branch, rebase_branch, autosquash_rebase, abort_merge, abort_rebase, continue_rebase, continue_merge,
+    get_reflog, create_branch_from_reflog, checkout_reflog_entry,
     get_tags, create_tag, delete_tag, delete_remote_tag,
     get_remotes, add_remote, remove_remote, fetch_remote, pull_remote, push_remote, test_remote_connection,
     get_remote_auth_config, set_remote_auth_config, remove_remote_auth_config,
     check_ssh_key, convert_ssh_key, get_converted_key_path_cmd,
+    get_signing_config,
     ssh_key_needs_unlock, ssh_key_is_unlocked, ssh_key_unlock, ssh_key_lock, ssh_keys_lock_all,
     get_stashes, create_stash, apply_stash, pop_stash, drop_stash, clear_stashes,
+    export_working_diff, export_commits, apply_patch, check_patch, abort_am, continue_am, skip_am,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -45,20 +57,50 @@ pub fn run() {
             discard_changes,
             get_file_diff,
             get_commit_diff,
+            get_file_diff_at_commit,
             get_stash_diff,
             get_untracked_file_diff,
+            get_diff_hunks,
             stage_lines,
             unstage_lines,
+            discard_lines,
+            stage_hunks,
+            unstage_hunks,
+            discard_hunks,
             get_blob_base64,
             create_commit,
+            create_fixup_commit,
             get_last_commit_message,
             cherry_pick,
+            cherry_pick_commits,
             revert_commit,
             abort_cherry_pick,
             abort_revert,
             continue_cherry_pick,
+            skip_cherry_pick,
             continue_revert,
+            list_hooks,
+            set_hook_enabled,
+            get_commit_templates,
+            lint_commit_message,
             get_commit_log,
+            get_commit_details,
+            get_commit_graph,
+            get_file_log,
+            get_commit_signatures,
+            locate_commit,
+            search_commits,
+            blame_file,
+            blame_file_incremental,
+            get_repository_stats,
+            get_mailmap,
+            set_mailmap,
+            check_mailmap,
+            get_commit_notes,
+            get_display_notes_refs,
+            add_note,
+            edit_note,
+            remove_note,
             get_branches,
             create_branch,
             delete_branch,
@@ -67,10 +109,14 @@ pub fn run() {
             delete_remote_branch,
             merge_branch,
             rebase_branch,
+            autosquash_rebase,
             abort_merge,
             abort_rebase,
             continue_rebase,
             continue_merge,
+            get_reflog,
+            create_branch_from_reflog,
+            checkout_reflog_entry,
             get_tags,
             create_tag,
             delete_tag,
@@ -88,6 +134,7 @@ pub fn run() {
             check_ssh_key,
             convert_ssh_key,
             get_converted_key_path_cmd,
+            get_signing_config,
             ssh_key_needs_unlock,
             ssh_key_is_unlocked,
             ssh_key_unlock,
@@ -98,7 +145,14 @@ pub fn run() {
             apply_stash,
             pop_stash,
             drop_stash,
-            clear_stashes
+            clear_stashes,
+            export_working_diff,
+            export_commits,
+            apply_patch,
+            check_patch,
+            abort_am,
+            continue_am,
+            skip_am
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");